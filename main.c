@@ -24,4 +24,50 @@ void rust_panic_wrap(void)
 	k_panic();
 }
 
+#ifdef CONFIG_LOG
+#include <zephyr/logging/log.h>
+
+/* Messages from Rust modules without a log source of their own come from this one.  Rust modules
+ * define their own sources with `log_module_register!`, in the same table as those from C.
+ */
+LOG_MODULE_REGISTER(rust, CONFIG_RUST_LOG_LEVEL);
+
+#ifdef CONFIG_LOG_RUNTIME_FILTERING
+TYPE_SECTION_START_EXTERN(struct log_source_dynamic_data, log_dynamic);
+#endif
+
+/* The `rust` source, or NULL if its level is zero. */
+const struct log_source_const_data *rust_log_default_source(void)
+{
+	return __log_current_const_data;
+}
+
+/* Log a message that has already been formatted by Rust, from the given source.  The level has
+ * already been checked against the source's level, but not the runtime filter.  In deferred
+ * mode, the string is copied into the log message, as the Rust buffer does not outlive this call.
+ */
+void rust_log_message(const struct log_source_const_data *source, uint32_t level, const char *msg)
+{
+#ifdef CONFIG_LOG_MODE_MINIMAL
+	ARG_UNUSED(source);
+	printk("%c: %s\n", z_log_minimal_level_to_char(level), msg);
+#else
+	const void *log_source = source;
+
+#ifdef CONFIG_LOG_RUNTIME_FILTERING
+	/* The filters are at the same index in their table as the source is in its own. */
+	struct log_source_dynamic_data *dynamic =
+		&TYPE_SECTION_START(log_dynamic)[log_const_source_id(source)];
+
+	if (level > Z_LOG_RUNTIME_FILTER(dynamic->filters)) {
+		return;
+	}
+	log_source = dynamic;
+#endif
+
+	z_log_msg_runtime_create(Z_LOG_LOCAL_DOMAIN_ID, log_source, level, NULL, 0, 0, "%s", msg);
+#endif
+}
+#endif
+
 #endif
@@ -0,0 +1,186 @@
+#!/usr/bin/env python3
+# Copyright (c) 2024 Linaro LTD
+# SPDX-License-Identifier: Apache-2.0
+
+r"""Make the Kconfig settings available to the Rust build.
+
+This is run by `rust_cargo_application()`, with the same environment as the other Kconfig tools,
+after the configuration has been written.  It loads the Kconfig tree along with the generated
+.config, and writes two files.
+
+The first is a `rustflags` setting, to be placed in the `[build]` table of a Cargo config.  These
+enable a cfg for each bool Kconfig symbol that is set, and declare all of the bool symbols with
+`--check-cfg`, so that any crate in the build can use `#[cfg(CONFIG_...)]` without warnings,
+whether the symbol is set or not.  Any flags the target needs are given with `--target-rustflag`,
+and are placed first, as Cargo only uses a single `rustflags` setting.
+
+The second describes each symbol with its type, so that `zephyr-build` can generate correctly
+typed constants, which the .config alone doesn't allow.  There is one line for each symbol, and
+one for each named choice, with tab separated fields:
+
+    bool    NAME  y|n                       PROMPT
+    int     NAME  VALUE  LOW  HIGH          PROMPT
+    hex     NAME  VALUE  LOW  HIGH          PROMPT
+    string  NAME  VALUE                     PROMPT
+    choice  NAME  SELECTION  SYMBOLS        PROMPT
+
+Names include the `CONFIG_` prefix.  `LOW` and `HIGH` are the active range, and are empty when
+there is none.  Int values are in decimal, and hex values in hex with a `0x` prefix.  The
+`SELECTION` is empty if no symbol of the choice is selected, and `SYMBOLS` is space separated.
+Backslashes, tabs and newlines in strings and prompts are escaped as `\\`, `\t` and `\n`.
+"""
+
+import argparse
+import os
+import sys
+
+sys.path.insert(0, os.path.join(os.environ["ZEPHYR_BASE"], "scripts", "kconfig"))
+
+import kconfiglib  # noqa: E402
+
+# The number of names given to each `--check-cfg`, to keep each argument a reasonable size.
+CHECK_CFG_CHUNK = 64
+
+
+def parse_args():
+    parser = argparse.ArgumentParser(description=__doc__, allow_abbrev=False)
+    parser.add_argument("kconfig_root", help="the top level Kconfig file")
+    parser.add_argument("dotconfig", help="the generated .config")
+    parser.add_argument("--rustflags-out", required=True,
+                        help="file to write the rustflags setting to")
+    parser.add_argument("--symbols-out", required=True,
+                        help="file to write the symbol descriptions to")
+    parser.add_argument("--target-rustflag", action="append", default=[],
+                        help="a flag needed by the target, can be given more than once")
+    return parser.parse_args()
+
+
+def bool_symbols(kconf):
+    """The bool and tristate symbols, sorted by name."""
+    syms = [sym for sym in kconf.unique_defined_syms
+            if sym.type in (kconfiglib.BOOL, kconfiglib.TRISTATE)]
+    return sorted(syms, key=lambda sym: sym.name)
+
+
+def rustflags(kconf, target_flags):
+    prefix = kconf.config_prefix
+    syms = bool_symbols(kconf)
+
+    flags = list(target_flags)
+    for sym in syms:
+        if sym.str_value == "y":
+            flags += ["--cfg", prefix + sym.name]
+
+    names = [prefix + sym.name for sym in syms]
+    for start in range(0, len(names), CHECK_CFG_CHUNK):
+        chunk = names[start:start + CHECK_CFG_CHUNK]
+        flags += ["--check-cfg", "cfg({})".format(", ".join(chunk))]
+
+    return flags
+
+
+def rustflags_text(flags):
+    """The flags as a TOML key and array.  The names need no escaping."""
+    lines = ["rustflags = ["]
+    lines += ['    "{}",'.format(flag) for flag in flags]
+    lines.append("]")
+    return "".join(line + "\n" for line in lines)
+
+
+def escape(text):
+    return text.replace("\\", "\\\\").replace("\t", "\\t").replace("\n", "\\n")
+
+
+def prompt(item):
+    """The first prompt of a symbol or choice, or an empty string if it has none."""
+    for node in item.nodes:
+        if node.prompt:
+            return node.prompt[0]
+    return ""
+
+
+def active_range(sym, base):
+    """The low and high values of the first range whose condition holds, or None."""
+    for low, high, cond in sym.ranges:
+        if kconfiglib.expr_value(cond):
+            try:
+                return int(low.str_value, base), int(high.str_value, base)
+            except ValueError:
+                return None
+    return None
+
+
+def symbol_line(kconf, sym):
+    """The description of a single symbol, or None for a symbol with no value."""
+    name = kconf.config_prefix + sym.name
+
+    if sym.type in (kconfiglib.BOOL, kconfiglib.TRISTATE):
+        value = "y" if sym.str_value == "y" else "n"
+        return ["bool", name, value, prompt(sym)]
+
+    # Other symbols only have a value when they are written to the .config.
+    if not sym.config_string:
+        return None
+
+    if sym.type in (kconfiglib.INT, kconfiglib.HEX):
+        base = 10 if sym.type == kconfiglib.INT else 16
+        value = int(sym.str_value, base)
+        bounds = active_range(sym, base)
+        if sym.type == kconfiglib.INT:
+            fields = ["int", name, str(value)]
+            fields += [str(b) for b in bounds] if bounds else ["", ""]
+        else:
+            fields = ["hex", name, hex(value)]
+            fields += [hex(b) for b in bounds] if bounds else ["", ""]
+        return fields + [prompt(sym)]
+
+    if sym.type == kconfiglib.STRING:
+        return ["string", name, sym.str_value, prompt(sym)]
+
+    return None
+
+
+def choice_line(kconf, choice):
+    """The description of a named choice.  Unnamed choices have no useful name to give them."""
+    if not choice.name or not choice.syms:
+        return None
+    prefix = kconf.config_prefix
+    selection = prefix + choice.selection.name if choice.selection else ""
+    syms = " ".join(prefix + sym.name for sym in choice.syms)
+    return ["choice", prefix + choice.name, selection, syms, prompt(choice)]
+
+
+def symbols_text(kconf):
+    lines = []
+    for sym in sorted(kconf.unique_defined_syms, key=lambda sym: sym.name):
+        lines.append(symbol_line(kconf, sym))
+    for choice in kconf.unique_choices:
+        lines.append(choice_line(kconf, choice))
+    return "".join("\t".join(escape(field) for field in line) + "\n"
+                   for line in lines if line)
+
+
+def write_if_changed(path, text):
+    """Write the file, leaving it alone if it is unchanged, so that Cargo doesn't rebuild."""
+    try:
+        with open(path) as old:
+            if old.read() == text:
+                return
+    except OSError:
+        pass
+    with open(path, "w") as out:
+        out.write(text)
+
+
+def main():
+    args = parse_args()
+
+    kconf = kconfiglib.Kconfig(args.kconfig_root, warn=False)
+    kconf.load_config(args.dotconfig)
+
+    write_if_changed(args.rustflags_out, rustflags_text(rustflags(kconf, args.target_rustflag)))
+    write_if_changed(args.symbols_out, symbols_text(kconf))
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,125 @@
+// Copyright (c) 2024 Linaro LTD
+// SPDX-License-Identifier: Apache-2.0
+
+#![no_std]
+
+use zephyr::printkln;
+use zephyr::raw;
+use zephyr::sync::channel::{Channel, RecvError, SendError};
+use zephyr::sync::{Condvar, Mutex, MutexError, MutexGuard, Semaphore};
+use zephyr::thread::{Builder, Thread};
+use zephyr::time::{Duration, Forever, NoWait};
+
+zephyr::thread_stack!(OTHER_STACK, 1024);
+static OTHER: Thread = Thread::new();
+
+/// The mutex the lock tests use.
+static SHARED: Mutex<u32> = Mutex::new(0);
+
+/// The result of `try_lock` from the other thread.
+static LOCK_RESULT: Channel<Option<MutexError>, 1> = Channel::new();
+
+#[no_mangle]
+extern "C" fn rust_main() {
+    check_recursive_lock();
+    check_contended_lock();
+    check_semaphore();
+    check_channel();
+    check_condvar_timeout();
+    printkln!("All tests passed");
+}
+
+/// Try to lock `SHARED` from another thread, returning the error, if any.
+fn try_lock_from_other_thread() -> Option<MutexError> {
+    let mut handle = Builder::new()
+        .spawn(&OTHER, &OTHER_STACK, || {
+            let result = SHARED.try_lock().map(|_| ()).err();
+            let (tx, _) = LOCK_RESULT.split();
+            tx.send(result).unwrap();
+        })
+        .unwrap();
+    handle.join(Forever).unwrap();
+    let (_, rx) = LOCK_RESULT.split();
+    rx.recv().unwrap()
+}
+
+/// A second lock from the owning thread is refused, and doesn't leave the mutex locked twice.
+fn check_recursive_lock() {
+    printkln!("Testing: recursive lock");
+    let mut guard = SHARED.lock().unwrap();
+    *guard += 1;
+    assert_eq!(SHARED.lock().err(), Some(MutexError::Recursive));
+    assert_eq!(SHARED.try_lock().err(), Some(MutexError::Recursive));
+    assert_eq!(SHARED.try_lock_for(Duration::millis(10)).err(), Some(MutexError::Recursive));
+
+    // Still held by this thread.
+    assert_eq!(try_lock_from_other_thread(), Some(MutexError::WouldBlock));
+
+    // A single unlock releases it, so the failed attempts didn't add to the lock count.
+    MutexGuard::unlock(guard).unwrap();
+    assert_eq!(try_lock_from_other_thread(), None);
+    assert_eq!(*SHARED.lock().unwrap(), 1);
+}
+
+/// `try_lock` of a mutex held by another thread fails without waiting.
+fn check_contended_lock() {
+    printkln!("Testing: contended lock");
+    let guard = SHARED.lock().unwrap();
+    assert_eq!(try_lock_from_other_thread(), Some(MutexError::WouldBlock));
+    drop(guard);
+    assert_eq!(try_lock_from_other_thread(), None);
+}
+
+fn check_semaphore() {
+    printkln!("Testing: semaphore");
+    static SEM: Semaphore = Semaphore::new(1, 2);
+
+    SEM.take(NoWait).unwrap();
+    assert_eq!(SEM.take(NoWait).unwrap_err().0, raw::EBUSY);
+    assert_eq!(SEM.take(Duration::millis(10)).unwrap_err().0, raw::EAGAIN);
+
+    // The count doesn't go past the limit.
+    SEM.give();
+    SEM.give();
+    SEM.give();
+    assert_eq!(SEM.count(), 2);
+    SEM.reset();
+    assert_eq!(SEM.count(), 0);
+}
+
+fn check_channel() {
+    printkln!("Testing: channel");
+    static CHANNEL: Channel<u32, 2> = Channel::new();
+    let (tx, rx) = CHANNEL.split();
+
+    tx.try_send(1).unwrap();
+    tx.send_timeout(2, Duration::millis(10)).unwrap();
+    assert_eq!(tx.try_send(3), Err(SendError::Full(3)));
+    assert_eq!(tx.send_timeout(4, Duration::millis(10)), Err(SendError::TimedOut(4)));
+    assert_eq!(tx.num_used(), 2);
+
+    assert_eq!(rx.peek(), Some(1));
+    assert_eq!(rx.recv().unwrap(), 1);
+    assert_eq!(rx.try_recv().unwrap(), 2);
+    assert_eq!(rx.try_recv(), Err(RecvError::Empty));
+    assert_eq!(rx.recv_timeout(Duration::millis(10)), Err(RecvError::TimedOut));
+    assert_eq!(rx.peek(), None);
+
+    tx.send(5).unwrap();
+    rx.purge();
+    assert_eq!(rx.num_used(), 0);
+}
+
+fn check_condvar_timeout() {
+    printkln!("Testing: condvar timeout");
+    static READY: Mutex<bool> = Mutex::new(false);
+    static CHANGED: Condvar = Condvar::new();
+
+    let ready = READY.lock().unwrap();
+    let (ready, result) = CHANGED.wait_timeout(ready, Duration::millis(10)).unwrap();
+    assert!(result.timed_out());
+    assert!(!*ready);
+
+    // The mutex is held again after the wait.
+    assert_eq!(READY.try_lock().err(), Some(MutexError::Recursive));
+}
@@ -9,13 +9,19 @@ use core::ffi::{
 };
 
 use zephyr::printkln;
-use zephyr::time::{Duration, Instant, Tick, Timeout};
-use zephyr::raw::k_timeout_t;
+use zephyr::time::{Duration, Instant, Tick, Timeout, TimeoutError};
+use zephyr::raw::{k_ticks_t, k_timeout_t};
 
 #[no_mangle]
 extern "C" fn rust_main() {
     printkln!("Tick frequency: {}", zephyr::time::SYS_FREQUENCY);
     check_conversions();
+    check_wrapping();
+    check_duration_saturation();
+    #[cfg(CONFIG_TIMEOUT_64BIT)]
+    check_instant_saturation();
+    #[cfg(not(CONFIG_TIMEOUT_64BIT))]
+    check_relative_instants();
     printkln!("All tests passed");
 }
 
@@ -71,6 +77,82 @@ fn check_conversions() {
     }
 }
 
+/// Verify that Instants compare and subtract correctly across the wrap of the tick counter.  This
+/// is only reachable at runtime with 32-bit ticks, but the arithmetic is the same for both.
+fn check_wrapping() {
+    printkln!("Testing: wrapping");
+    let before = Instant::from_ticks(Tick::MAX - 9);
+    let after = before + Duration::from_ticks(20);
+    assert_eq!(after.ticks(), 10);
+
+    assert!(after > before);
+    assert!(before < after);
+    assert_eq!(after.duration_since(before), Duration::from_ticks(20));
+    assert_eq!(after - before, Duration::from_ticks(20));
+    assert_eq!(before.checked_duration_since(after), None);
+    assert_eq!(before.duration_since(after), Duration::from_ticks(0));
+    assert_eq!(after - Duration::from_ticks(20), before);
+
+    // Just under half of the range apart is still ordered across the wrap.
+    let far = before + Duration::from_ticks(Tick::MAX / 2 - 1);
+    assert!(far > before);
+    assert_eq!(far.duration_since(before), Duration::from_ticks(Tick::MAX / 2 - 1));
+}
+
+/// A Duration too long for a timeout is an error from `try_from_duration`, and saturates to the
+/// longest timeout through `From`, rather than wrapping to `K_FOREVER` or an absolute timeout.
+fn check_duration_saturation() {
+    printkln!("Testing: duration saturation");
+    let longest = Duration::from_ticks(k_ticks_t::MAX as Tick);
+    let timeout = Timeout::try_from_duration(longest).unwrap();
+    assert_eq!(timeout.0.ticks, k_ticks_t::MAX);
+
+    for ticks in [k_ticks_t::MAX as Tick + 1, Tick::MAX] {
+        let value = Duration::from_ticks(ticks);
+        assert_eq!(Timeout::try_from_duration(value).err(), Some(TimeoutError));
+        let timeout: Timeout = value.into();
+        assert_eq!(timeout.0.ticks, k_ticks_t::MAX);
+    }
+}
+
+/// An Instant too far in the future for an absolute timeout is an error from `try_from_instant`,
+/// and saturates to the latest absolute timeout, which is encoded as `k_ticks_t::MIN`, through
+/// `From`.
+#[cfg(CONFIG_TIMEOUT_64BIT)]
+fn check_instant_saturation() {
+    printkln!("Testing: instant saturation");
+    let latest = Instant::from_ticks(k_ticks_t::MAX as Tick - 1);
+    let timeout = Timeout::try_from_instant(latest).unwrap();
+    assert_eq!(timeout.0.ticks, k_ticks_t::MIN);
+
+    for ticks in [k_ticks_t::MAX as Tick, Tick::MAX] {
+        let value = Instant::from_ticks(ticks);
+        assert_eq!(Timeout::try_from_instant(value).err(), Some(TimeoutError));
+        let timeout: Timeout = value.into();
+        assert_eq!(timeout.0.ticks, k_ticks_t::MIN);
+    }
+}
+
+/// Without 64-bit timeouts, an Instant becomes a timeout relative to the time of the conversion.
+#[cfg(not(CONFIG_TIMEOUT_64BIT))]
+fn check_relative_instants() {
+    printkln!("Testing: relative instants");
+    let now = Instant::now();
+
+    let timeout: Timeout = (now + Duration::from_ticks(100)).into();
+    assert!(timeout.0.ticks > 0 && timeout.0.ticks <= 100);
+
+    // Deadlines that have passed, including ones on the other side of a wrap, don't wait.
+    let timeout: Timeout = (now - Duration::from_ticks(1)).into();
+    assert_eq!(timeout.0.ticks, zephyr::sys::K_NO_WAIT.ticks);
+    let timeout: Timeout = (now - Duration::from_ticks(Tick::MAX / 4)).into();
+    assert_eq!(timeout.0.ticks, zephyr::sys::K_NO_WAIT.ticks);
+
+    // A deadline far ahead, which may be past the wrap, is still in the future.
+    let timeout: Timeout = (now + Duration::from_ticks(Tick::MAX / 4)).into();
+    assert!(timeout.0.ticks > 0 && timeout.0.ticks as Tick <= Tick::MAX / 4);
+}
+
 /// The time entry information.
 #[repr(C)]
 struct TimeEntry {
@@ -0,0 +1,64 @@
+// Copyright (c) 2024 Linaro LTD
+// SPDX-License-Identifier: Apache-2.0
+
+#![no_std]
+
+use zephyr::drivers::uart::{BufferedUart, Uart};
+use zephyr::printkln;
+use zephyr::raw;
+use zephyr::sync::Mutex;
+use zephyr::thread::{Builder, Thread};
+use zephyr::time::Duration;
+
+/// The number of writes each writer completes.
+const WRITES: usize = 50;
+
+zephyr::thread_stack!(FIRST_STACK, 1024);
+static FIRST: Thread = Thread::new();
+zephyr::thread_stack!(SECOND_STACK, 1024);
+static SECOND: Thread = Thread::new();
+
+/// The console UART, shared by both writers.
+static CONSOLE: BufferedUart<16> = BufferedUart::new();
+
+/// The number of writes refused with `EBUSY`, across both writers.
+static BUSY: Mutex<usize> = Mutex::new(0);
+
+#[no_mangle]
+extern "C" fn rust_main() {
+    let uart = Uart::from_dt(&zephyr::devicetree::chosen::zephyr_console::NODE).unwrap();
+    CONSOLE.attach_irq(uart).unwrap();
+    check_racing_writers();
+    printkln!("All tests passed");
+}
+
+/// Write `data` until it has been sent `WRITES` times, retrying when the other writer has the
+/// transmitter.
+fn write_repeatedly(data: &[u8]) {
+    let mut done = 0;
+    while done < WRITES {
+        match CONSOLE.write(data) {
+            Ok(()) => done += 1,
+            Err(e) if e.0 == raw::EBUSY => {
+                *BUSY.lock().unwrap() += 1;
+                zephyr::time::yield_now();
+            }
+            Err(e) => panic!("Write failed: {}", e),
+        }
+    }
+}
+
+/// Two threads writing at once.  A writer refused with `EBUSY` must not disturb the completion of
+/// the write in progress, so both writers finish, rather than one waiting forever.
+fn check_racing_writers() {
+    printkln!("Testing: racing writers");
+    let mut first = Builder::new()
+        .spawn(&FIRST, &FIRST_STACK, || write_repeatedly(b"first\r\n"))
+        .unwrap();
+    let mut second = Builder::new()
+        .spawn(&SECOND, &SECOND_STACK, || write_repeatedly(b"second\r\n"))
+        .unwrap();
+    first.join(Duration::secs(10)).expect("first writer did not finish");
+    second.join(Duration::secs(10)).expect("second writer did not finish");
+    printkln!("Writes refused while busy: {}", *BUSY.lock().unwrap());
+}
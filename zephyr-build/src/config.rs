@@ -0,0 +1,350 @@
+// Copyright (c) 2024 Linaro LTD
+// SPDX-License-Identifier: Apache-2.0
+
+//! Kconfig values for build scripts.
+//!
+//! A [`Config`] holds the settings from the generated .config, so that a `build.rs` can make
+//! decisions based on them, such as choosing what code to generate, or sizing a table.
+//!
+//! ```ignore
+//! use zephyr_build::Config;
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let config = Config::load()?;
+//!     if config.is_enabled("CONFIG_BT") {
+//!         let slots = config.get_int("CONFIG_BT_MAX_CONN")?;
+//!         // ...
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The value of a setting in the .config.
+///
+/// The .config doesn't record the types of the settings, so a value is classified by its form.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Value {
+    /// `y`, or a disabled setting, written as `# CONFIG_FOO is not set`.  Zephyr doesn't have
+    /// loadable modules, so a tristate of `m` is false.
+    Bool(bool),
+    /// A decimal number.
+    Int(i64),
+    /// A number given in hex.
+    Hex(u64),
+    /// A quoted string, with the escapes removed.
+    Str(String),
+}
+
+/// The errors from loading a .config, or looking up a value.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The `DOTCONFIG` environment variable, giving the path of the .config, is not set.
+    NoDotconfig,
+    /// The .config could not be read.
+    Io { path: PathBuf, error: io::Error },
+    /// A line of the .config could not be understood.
+    Parse { line: usize, message: String },
+    /// The setting is not in the .config.
+    NotFound(String),
+    /// The setting is not of the type asked for.
+    WrongType { name: String, expected: &'static str },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::NoDotconfig => write!(f, "DOTCONFIG must be set by wrapper"),
+            ConfigError::Io { path, error } => write!(f, "{}: {}", path.display(), error),
+            ConfigError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+            ConfigError::NotFound(name) => write!(f, "{} is not set", name),
+            ConfigError::WrongType { name, expected } => {
+                write!(f, "{} is not {}", name, expected)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Io { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+type Result<T> = std::result::Result<T, ConfigError>;
+
+/// The settings of a .config.
+///
+/// Names can be given with or without the `CONFIG_` prefix.
+#[derive(Debug, Default)]
+pub struct Config {
+    /// The settings, in the order of the .config.
+    entries: Vec<(String, Value)>,
+    /// Index into `entries` by name.
+    index: HashMap<String, usize>,
+}
+
+impl Config {
+    /// Load the .config given by the `DOTCONFIG` environment variable, which is set by the Zephyr
+    /// build.  Cargo is told to rerun the build script if either the variable or the file changes.
+    pub fn load() -> Result<Config> {
+        println!("cargo:rerun-if-env-changed=DOTCONFIG");
+        let path = env::var_os("DOTCONFIG").ok_or(ConfigError::NoDotconfig)?;
+        Config::from_path(path)
+    }
+
+    /// Load the .config from the given path.  Cargo is told to rerun the build script if it
+    /// changes.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Config> {
+        let path = path.as_ref();
+        println!("cargo:rerun-if-changed={}", path.display());
+        let text = fs::read_to_string(path)
+            .map_err(|error| ConfigError::Io { path: path.to_path_buf(), error })?;
+        Config::parse(&text)
+    }
+
+    /// Parse the text of a .config.
+    pub fn parse(text: &str) -> Result<Config> {
+        let mut config = Config::default();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            let entry = if let Some(comment) = line.strip_prefix('#') {
+                match comment.trim().strip_suffix(" is not set") {
+                    Some(name) => (name.to_string(), Value::Bool(false)),
+                    None => continue,
+                }
+            } else if let Some((name, value)) = line.split_once('=') {
+                let value = parse_value(value).map_err(|message| ConfigError::Parse {
+                    line: index + 1,
+                    message,
+                })?;
+                (name.to_string(), value)
+            } else if line.is_empty() {
+                continue;
+            } else {
+                return Err(ConfigError::Parse {
+                    line: index + 1,
+                    message: format!("expected a setting: {:?}", line),
+                });
+            };
+            config.insert(entry.0, entry.1);
+        }
+        Ok(config)
+    }
+
+    fn insert(&mut self, name: String, value: Value) {
+        match self.index.get(&name) {
+            // A later setting of the same name replaces the earlier one.
+            Some(&index) => self.entries[index].1 = value,
+            None => {
+                self.index.insert(name.clone(), self.entries.len());
+                self.entries.push((name, value));
+            }
+        }
+    }
+
+    /// Look up a setting.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        let index = match self.index.get(name) {
+            Some(index) => index,
+            None => self.index.get(&format!("CONFIG_{}", name))?,
+        };
+        Some(&self.entries[*index].1)
+    }
+
+    /// Determine if a bool setting is enabled.  Settings that are disabled, or not present at all,
+    /// are both false.
+    pub fn is_enabled(&self, name: &str) -> bool {
+        matches!(self.get(name), Some(Value::Bool(true)))
+    }
+
+    /// Get the value of an int or hex setting.  A hex value too large for an `i64` is a
+    /// `WrongType` error.
+    pub fn get_int(&self, name: &str) -> Result<i64> {
+        match self.lookup(name)? {
+            Value::Int(value) => Ok(*value),
+            Value::Hex(value) => i64::try_from(*value).map_err(|_| wrong_type(name, "an i64")),
+            _ => Err(wrong_type(name, "an int")),
+        }
+    }
+
+    /// Get the value of a string setting.
+    pub fn get_str(&self, name: &str) -> Result<&str> {
+        match self.lookup(name)? {
+            Value::Str(value) => Ok(value),
+            _ => Err(wrong_type(name, "a string")),
+        }
+    }
+
+    /// Iterate over the settings, including the disabled ones, in the order of the .config.  The
+    /// names include the `CONFIG_` prefix.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.entries.iter().map(|(name, value)| (name.as_str(), value))
+    }
+
+    fn lookup(&self, name: &str) -> Result<&Value> {
+        self.get(name).ok_or_else(|| ConfigError::NotFound(name.to_string()))
+    }
+}
+
+fn wrong_type(name: &str, expected: &'static str) -> ConfigError {
+    ConfigError::WrongType { name: name.to_string(), expected }
+}
+
+/// Classify a value by its form.
+fn parse_value(text: &str) -> std::result::Result<Value, String> {
+    if text == "y" {
+        Ok(Value::Bool(true))
+    } else if text == "n" || text == "m" {
+        Ok(Value::Bool(false))
+    } else if let Some(quoted) = text.strip_prefix('"') {
+        unescape(quoted).map(Value::Str)
+    } else if let Some(digits) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        u64::from_str_radix(digits, 16)
+            .map(Value::Hex)
+            .map_err(|_| format!("invalid hex value: {}", text))
+    } else {
+        text.parse().map(Value::Int).map_err(|_| format!("unrecognized value: {}", text))
+    }
+}
+
+/// Remove the escapes from the body of a string, after the opening quote, up to the closing
+/// quote.  Kconfig only escapes quotes and backslashes.
+fn unescape(quoted: &str) -> std::result::Result<String, String> {
+    let mut result = String::new();
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if chars.as_str().is_empty() => return Ok(result),
+            '"' => break,
+            '\\' => match chars.next() {
+                Some(c) => result.push(c),
+                None => break,
+            },
+            c => result.push(c),
+        }
+    }
+    Err(format!("badly quoted string: \"{}", quoted))
+}
+
+impl<'a> IntoIterator for &'a Config {
+    type Item = (&'a str, &'a Value);
+    type IntoIter = Box<dyn Iterator<Item = (&'a str, &'a Value)> + 'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Box::new(self.iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOTCONFIG: &str = "\
+#
+# Automatically generated file; DO NOT EDIT.
+# Zephyr Kernel Configuration
+#
+CONFIG_BOARD=\"nrf52840dk\"
+CONFIG_NUM_IRQS=48
+CONFIG_SRAM_BASE_ADDRESS=0x20000000
+CONFIG_TRISTATE=m
+CONFIG_NEGATIVE=-5
+
+# CONFIG_DISABLED is not set
+CONFIG_ENABLED=y
+CONFIG_ESCAPED=\"say \\\"hi\\\" \\\\ there\"
+CONFIG_BIG_HEX=0xffffffffffffffff
+";
+
+    #[test]
+    fn values() {
+        let config = Config::parse(DOTCONFIG).unwrap();
+        assert_eq!(config.get("CONFIG_BOARD"), Some(&Value::Str("nrf52840dk".to_string())));
+        assert_eq!(config.get("CONFIG_NUM_IRQS"), Some(&Value::Int(48)));
+        assert_eq!(config.get("CONFIG_SRAM_BASE_ADDRESS"), Some(&Value::Hex(0x2000_0000)));
+        assert_eq!(config.get("CONFIG_TRISTATE"), Some(&Value::Bool(false)));
+        assert_eq!(config.get("CONFIG_NEGATIVE"), Some(&Value::Int(-5)));
+        assert_eq!(config.get("CONFIG_DISABLED"), Some(&Value::Bool(false)));
+        assert_eq!(config.iter().count(), 9);
+        assert!(config.is_enabled("CONFIG_ENABLED"));
+        assert!(!config.is_enabled("CONFIG_DISABLED"));
+        assert!(!config.is_enabled("CONFIG_TRISTATE"));
+        assert!(!config.is_enabled("CONFIG_MISSING"));
+    }
+
+    #[test]
+    fn comments() {
+        let config = Config::parse("# A comment\n#\n# CONFIG_FOO is not set\n").unwrap();
+        let entries: Vec<_> = config.iter().collect();
+        assert_eq!(entries, [("CONFIG_FOO", &Value::Bool(false))]);
+    }
+
+    #[test]
+    fn strings() {
+        let config = Config::parse(DOTCONFIG).unwrap();
+        assert_eq!(config.get_str("CONFIG_ESCAPED").unwrap(), "say \"hi\" \\ there");
+        assert_eq!(unescape("\\\\\"").unwrap(), "\\");
+        assert_eq!(unescape("\"").unwrap(), "");
+    }
+
+    #[test]
+    fn bad_strings() {
+        for text in ["CONFIG_A=\"unterminated", "CONFIG_A=\"early\" end\"", "CONFIG_A=\"esc\\\""] {
+            let text = format!("CONFIG_OK=y\n\n{}\n", text);
+            match Config::parse(&text) {
+                Err(ConfigError::Parse { line, message }) => {
+                    assert_eq!(line, 3);
+                    assert!(message.starts_with("badly quoted string"), "{}", message);
+                }
+                other => panic!("unexpected result {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        let cases = [("CONFIG_A\n", 1), ("CONFIG_A=y\nCONFIG_B=0xzz\n", 2), ("\nCONFIG_A=q\n", 2)];
+        for (text, line) in cases {
+            match Config::parse(text) {
+                Err(ConfigError::Parse { line: got, .. }) => assert_eq!(got, line, "{:?}", text),
+                other => panic!("unexpected result {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn ints() {
+        let config = Config::parse(DOTCONFIG).unwrap();
+        assert_eq!(config.get_int("CONFIG_NUM_IRQS").unwrap(), 48);
+        assert_eq!(config.get_int("CONFIG_SRAM_BASE_ADDRESS").unwrap(), 0x2000_0000);
+        assert!(matches!(
+            config.get_int("CONFIG_BIG_HEX"),
+            Err(ConfigError::WrongType { expected: "an i64", .. })
+        ));
+        assert!(matches!(
+            config.get_int("CONFIG_BOARD"),
+            Err(ConfigError::WrongType { expected: "an int", .. })
+        ));
+        assert!(matches!(config.get_str("CONFIG_NUM_IRQS"), Err(ConfigError::WrongType { .. })));
+        assert!(matches!(config.get_int("CONFIG_MISSING"), Err(ConfigError::NotFound(_))));
+    }
+
+    #[test]
+    fn prefix() {
+        let config = Config::parse("CONFIG_NUM_IRQS=48\nCONFIG_NUM_IRQS=64\n").unwrap();
+        assert_eq!(config.get("NUM_IRQS"), Some(&Value::Int(64)));
+        assert_eq!(config.get_int("NUM_IRQS").unwrap(), 64);
+        assert_eq!(config.iter().count(), 1);
+        assert_eq!(config.get("IRQS"), None);
+    }
+}
@@ -0,0 +1,207 @@
+// Copyright (c) 2024 Linaro LTD
+// SPDX-License-Identifier: Apache-2.0
+
+//! Devicetree support.
+//!
+//! The Zephyr build produces a final, flattened `zephyr.dts` containing the devicetree for the
+//! current board, after all overlays have been applied.  This module parses that file, and
+//! generates a Rust module describing it, so that applications can find the hardware without
+//! having to copy addresses and names by hand.
+
+use std::collections::HashMap;
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+
+mod ordinals;
+mod output;
+mod parse;
+
+pub use crate::parse_error::ParseError;
+
+/// A single node in the tree.
+#[derive(Debug)]
+pub struct Node {
+    /// The full name of the node, including any unit address.
+    pub name: String,
+    /// The full path from the root.
+    pub path: String,
+    /// The labels attached to this node.
+    pub labels: Vec<String>,
+    pub props: Vec<Property>,
+    /// Indices of the child nodes.
+    pub children: Vec<usize>,
+    /// Index of the parent, `None` only for the root.
+    pub parent: Option<usize>,
+    /// The dependency ordinal Zephyr assigned to the node, if known.
+    pub ord: Option<u32>,
+}
+
+impl Node {
+    fn new(name: String, path: String, parent: Option<usize>) -> Node {
+        Node {
+            name,
+            path,
+            labels: Vec::new(),
+            props: Vec::new(),
+            children: Vec::new(),
+            parent,
+            ord: None,
+        }
+    }
+
+    /// Look up a property by name.
+    pub fn prop(&self, name: &str) -> Option<&Property> {
+        self.props.iter().find(|p| p.name == name)
+    }
+
+    /// Return the value of a property that is a single cell.
+    pub fn prop_u32(&self, name: &str) -> Option<u32> {
+        match self.prop(name)?.values.as_slice() {
+            [Value::Cells(cells)] => match cells.as_slice() {
+                [Cell::Num(value)] => Some(*value as u32),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Return the value of a property that is a single string.
+    pub fn prop_str(&self, name: &str) -> Option<&str> {
+        match self.prop(name)?.values.as_slice() {
+            [Value::Str(text)] => Some(text),
+            _ => None,
+        }
+    }
+}
+
+/// A property, with its comma-separated list of values.  Boolean properties have no values.
+#[derive(Debug)]
+pub struct Property {
+    pub name: String,
+    pub values: Vec<Value>,
+}
+
+#[derive(Debug)]
+pub enum Value {
+    /// `< ... >`
+    Cells(Vec<Cell>),
+    /// `"..."`
+    Str(String),
+    /// `[ ... ]`
+    Bytes(Vec<u8>),
+    /// A reference outside of a cell list, which represents the path of the node.
+    Ref(Ref),
+    /// `/bits/ N < ... >`, for an element size of 8, 16 or 64 bits.  With 32 bits, this is just
+    /// `Cells`.
+    Bits(u32, Vec<u64>),
+}
+
+#[derive(Debug)]
+pub enum Cell {
+    Num(u64),
+    /// A phandle reference.
+    Ref(Ref),
+}
+
+#[derive(Debug)]
+pub enum Ref {
+    /// `&label`
+    Label(String),
+    /// `&{/path/to/node}`
+    Path(String),
+}
+
+/// A parsed devicetree.
+pub struct DeviceTree {
+    /// All of the nodes, the root is at index 0.
+    pub nodes: Vec<Node>,
+    labels: HashMap<String, usize>,
+    paths: HashMap<String, usize>,
+}
+
+impl DeviceTree {
+    /// Parse the text of a `zephyr.dts` file.
+    pub fn parse(text: &str) -> Result<DeviceTree, ParseError> {
+        let nodes = parse::parse(text)?;
+
+        let mut labels = HashMap::new();
+        let mut paths = HashMap::new();
+        for (index, node) in nodes.iter().enumerate() {
+            for label in &node.labels {
+                labels.insert(label.clone(), index);
+            }
+            paths.insert(node.path.clone(), index);
+        }
+
+        Ok(DeviceTree { nodes, labels, paths })
+    }
+
+    /// Fill in the node ordinals from the text of `devicetree_generated.h`.
+    pub fn set_ordinals(&mut self, header: &str) {
+        let ords = ordinals::parse(header);
+        for node in &mut self.nodes {
+            node.ord = ords.get(&node.path).copied();
+        }
+    }
+
+    /// Find a node by its path.
+    pub fn by_path(&self, path: &str) -> Option<usize> {
+        self.paths.get(path).copied()
+    }
+
+    /// Find a node by one of its labels.
+    pub fn by_label(&self, label: &str) -> Option<usize> {
+        self.labels.get(label).copied()
+    }
+
+    /// Resolve a reference to the node it refers to.
+    pub fn resolve(&self, reference: &Ref) -> Option<usize> {
+        match reference {
+            Ref::Label(label) => self.by_label(label),
+            Ref::Path(path) => self.by_path(path),
+        }
+    }
+
+    /// The address and size cell counts a node's children use for `reg`.  Defaults follow the
+    /// devicetree specification.
+    pub fn cell_sizes(&self, index: usize) -> (u32, u32) {
+        let node = &self.nodes[index];
+        (
+            node.prop_u32("#address-cells").unwrap_or(2),
+            node.prop_u32("#size-cells").unwrap_or(1),
+        )
+    }
+}
+
+/// Generate the devicetree module from the dts file given by `ZEPHYR_DTS`, along with the node
+/// ordinals from the header given by `DEVICETREE_GENERATED_H`.  The result is written to
+/// `devicetree.rs` in `OUT_DIR`, and is intended to be included by the `zephyr` crate, which
+/// provides the types the generated code refers to.
+pub fn build_dts() {
+    let dts_path = env::var("ZEPHYR_DTS").expect("ZEPHYR_DTS must be set by wrapper");
+    let header_path = env::var("DEVICETREE_GENERATED_H")
+        .expect("DEVICETREE_GENERATED_H must be set by wrapper");
+    let outdir = env::var("OUT_DIR").expect("OUT_DIR must be set");
+
+    println!("cargo:rerun-if-env-changed=ZEPHYR_DTS");
+    println!("cargo:rerun-if-env-changed=DEVICETREE_GENERATED_H");
+    println!("cargo:rerun-if-changed={}", dts_path);
+    println!("cargo:rerun-if-changed={}", header_path);
+
+    let text = fs::read_to_string(&dts_path).expect("Unable to read devicetree");
+    let mut tree = match DeviceTree::parse(&text) {
+        Ok(tree) => tree,
+        Err(e) => panic!("{}: {}", dts_path, e),
+    };
+    let header = fs::read_to_string(&header_path).expect("Unable to read devicetree header");
+    tree.set_ordinals(&header);
+
+    let gen_path = Path::new(&outdir).join("devicetree.rs");
+    let mut f = File::create(&gen_path).unwrap();
+    for warning in output::generate(&tree, &mut f).unwrap() {
+        println!("cargo:warning={}: {}", dts_path, warning);
+    }
+    f.flush().unwrap();
+}
@@ -0,0 +1,67 @@
+// Copyright (c) 2024 Linaro LTD
+// SPDX-License-Identifier: Apache-2.0
+
+//! Dependency ordinals from `devicetree_generated.h`.
+//!
+//! Zephyr numbers every node in the tree, and a device defined for a node is named after that
+//! number, as `__device_dts_ord_N`.  The numbers aren't in `zephyr.dts`, but the generated header
+//! has a pair of macros for each node, giving its path and ordinal:
+//!
+//! ```text
+//! #define DT_N_S_soc_S_uart_40002000_PATH "/soc/uart@40002000"
+//! #define DT_N_S_soc_S_uart_40002000_ORD 12
+//! ```
+
+use std::collections::HashMap;
+
+/// Find the ordinal of each node in the header, returning them by path.
+pub fn parse(text: &str) -> HashMap<String, u32> {
+    let mut paths = HashMap::new();
+    let mut ords = HashMap::new();
+
+    for line in text.lines() {
+        let Some(rest) = line.strip_prefix("#define ") else {
+            continue;
+        };
+        let Some((name, value)) = rest.split_once(char::is_whitespace) else {
+            continue;
+        };
+        let value = value.trim();
+        if let Some(id) = name.strip_suffix("_PATH") {
+            if let Some(path) = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+                paths.insert(id, path);
+            }
+        } else if let Some(id) = name.strip_suffix("_ORD") {
+            if let Ok(ord) = value.parse::<u32>() {
+                ords.insert(id, ord);
+            }
+        }
+    }
+
+    paths
+        .into_iter()
+        .filter_map(|(id, path)| Some((path.to_string(), *ords.get(id)?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ordinals() {
+        let ords = parse(
+            "#define DT_N_PATH \"/\"\n\
+             #define DT_N_ORD 0\n\
+             #define DT_N_S_soc_S_uart_40002000_PATH \"/soc/uart@40002000\"\n\
+             #define DT_N_S_soc_S_uart_40002000_FULL_NAME \"uart@40002000\"\n\
+             #define DT_N_S_soc_S_uart_40002000_ORD 12\n\
+             #define DT_N_S_soc_S_uart_40002000_ORD_STR_SORTABLE 00012\n\
+             #define DT_N_S_soc_S_uart_40002000_REQUIRES_ORDS 4,\n\
+             #define DT_N_S_soc_PATH \"/soc\"\n",
+        );
+        assert_eq!(ords.len(), 2);
+        assert_eq!(ords["/"], 0);
+        assert_eq!(ords["/soc/uart@40002000"], 12);
+    }
+}
@@ -0,0 +1,440 @@
+// Copyright (c) 2024 Linaro LTD
+// SPDX-License-Identifier: Apache-2.0
+
+//! Rust code generation for the devicetree.
+//!
+//! Each node becomes a module, nested the same way as the tree itself, with the root node being
+//! the `devicetree` module.  Every node module has a `NODE` constant, giving its path, name and
+//! device, along with a constant for each property that can be given a Rust type.  The nodes are
+//! also reachable through `labels`, `aliases`, and `chosen`, which just re-export the node modules.
+
+use std::collections::{HashMap, HashSet};
+use std::io::{Result, Write};
+
+use super::{Cell, DeviceTree, Value};
+
+/// Path to the module the generated code is included in.  The supporting types live there.
+const BASE: &str = "crate::devicetree";
+
+/// Names that are always generated, and can't be used for properties.
+const RESERVED_CONSTS: &[&str] = &["NODE", "LABELS"];
+
+/// Write the module, returning warnings about anything that had to be left out.
+pub fn generate(tree: &DeviceTree, w: &mut impl Write) -> Result<Vec<String>> {
+    let idents = node_idents(tree);
+    let gen = Generator { tree, idents, w, warnings: Vec::new() };
+    gen.generate()
+}
+
+struct Generator<'a, W> {
+    tree: &'a DeviceTree,
+    /// The module identifier of each node, indexed the same as the nodes.
+    idents: Vec<String>,
+    w: &'a mut W,
+    warnings: Vec<String>,
+}
+
+impl<'a, W: Write> Generator<'a, W> {
+    fn generate(mut self) -> Result<Vec<String>> {
+        writeln!(self.w, "// Generated devicetree for the current build.  Do not edit.")?;
+        self.node_consts(0, 0)?;
+
+        writeln!(self.w)?;
+        writeln!(self.w, "/// All nodes that have labels, by label.")?;
+        writeln!(self.w, "pub mod labels {{")?;
+        // Labels are case sensitive, but the module names are all lower case.
+        let mut seen: HashMap<String, &str> = HashMap::new();
+        for (index, node) in self.tree.nodes.iter().enumerate() {
+            for label in &node.labels {
+                let name = ident(label, false);
+                if let Some(other) = seen.get(&name) {
+                    let message = format!("label {:?} conflicts with label {:?}", label, other);
+                    writeln!(self.w, "    // Label {:?} conflicts with {:?}", label, other)?;
+                    self.warnings.push(message);
+                    continue;
+                }
+                writeln!(self.w, "    pub use {} as {};", self.module_path(index), name)?;
+                seen.insert(name, label);
+            }
+        }
+        writeln!(self.w, "}}")?;
+
+        for &child in &self.tree.nodes[0].children {
+            self.node_module(child, 0)?;
+        }
+        Ok(self.warnings)
+    }
+
+    /// Rust path to the module for a given node.
+    fn module_path(&self, index: usize) -> String {
+        let mut parts = Vec::new();
+        let mut cur = index;
+        while let Some(parent) = self.tree.nodes[cur].parent {
+            parts.push(self.idents[cur].as_str());
+            cur = parent;
+        }
+        parts.push(BASE);
+        parts.reverse();
+        parts.join("::")
+    }
+
+    fn node_module(&mut self, index: usize, depth: usize) -> Result<()> {
+        let pad = "    ".repeat(depth);
+        let node = &self.tree.nodes[index];
+        writeln!(self.w)?;
+        writeln!(self.w, "{}/// {}", pad, node.path)?;
+        writeln!(self.w, "{}pub mod {} {{", pad, self.idents[index])?;
+        self.node_consts(index, depth + 1)?;
+        for &child in &self.tree.nodes[index].children {
+            self.node_module(child, depth + 1)?;
+        }
+        writeln!(self.w, "{}}}", pad)?;
+        Ok(())
+    }
+
+    /// Output the constants describing a node.
+    fn node_consts(&mut self, index: usize, depth: usize) -> Result<()> {
+        let pad = "    ".repeat(depth);
+        let tree = self.tree;
+        let node = &tree.nodes[index];
+
+        // A device defined for the node is `__device_dts_ord_N`, which only exists if the node has
+        // a driver, so it is referenced weakly.
+        if let Some(ord) = node.ord {
+            writeln!(self.w, "{}device_ref!(device, {});", pad, ord)?;
+        }
+        writeln!(self.w, "{}pub const NODE: {}::Node = {}::Node {{", pad, BASE, BASE)?;
+        writeln!(self.w, "{}    path: {:?},", pad, node.path)?;
+        writeln!(self.w, "{}    name: {:?},", pad, node.name)?;
+        match node.ord {
+            Some(_) => writeln!(self.w, "{}    device: Some(device),", pad)?,
+            None => writeln!(self.w, "{}    device: None,", pad)?,
+        }
+        writeln!(self.w, "{}}};", pad)?;
+        writeln!(self.w, "{}pub const LABELS: &[&str] = &{:?};", pad, node.labels)?;
+
+        let special = node.parent == Some(0) && (node.name == "chosen" || node.name == "aliases");
+        let mut seen: HashSet<String> = RESERVED_CONSTS.iter().map(|s| s.to_string()).collect();
+        for prop in &node.props {
+            if special {
+                self.reexport(&pad, &prop.name, &prop.values)?;
+                continue;
+            }
+
+            let name = ident(&prop.name, true);
+            if !seen.insert(name.clone()) {
+                writeln!(self.w, "{}// Property {:?} conflicts with another name", pad, prop.name)?;
+                continue;
+            }
+
+            match self.prop_value(index, &prop.name, &prop.values) {
+                Some((ty, value)) => writeln!(self.w, "{}pub const {}: {} = {};", pad, name, ty, value)?,
+                None => writeln!(self.w, "{}// Property {:?} has no Rust representation", pad, prop.name)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// In the `chosen` and `aliases` nodes, each property refers to another node.  Make these
+    /// re-exports of that node's module.
+    fn reexport(&mut self, pad: &str, name: &str, values: &[Value]) -> Result<()> {
+        let target = match values {
+            [Value::Ref(reference)] => self.tree.resolve(reference),
+            [Value::Str(path)] => self.tree.by_path(path),
+            _ => None,
+        };
+        match target {
+            Some(target) => writeln!(self.w, "{}pub use {} as {};", pad, self.module_path(target), ident(name, false)),
+            None => writeln!(self.w, "{}// {:?} does not refer to a node", pad, name),
+        }
+    }
+
+    /// Determine the Rust type and value for a property.  Returns None if the property can't be
+    /// represented.
+    fn prop_value(&self, index: usize, name: &str, values: &[Value]) -> Option<(String, String)> {
+        match name {
+            "compatible" => {
+                let strs = strings(values)?;
+                return Some(("&[&str]".to_string(), format!("&{:?}", strs)));
+            }
+            "reg" => {
+                if let Some(reg) = self.reg(index, values) {
+                    return Some(reg);
+                }
+            }
+            "interrupts" => {
+                let cells = numbers(values)?;
+                return Some(("&[u32]".to_string(), format!("&{:?}", cells)));
+            }
+            _ => (),
+        }
+
+        // Otherwise, the type is determined by the form of the value.
+        match values {
+            [] => Some(("bool".to_string(), "true".to_string())),
+            [Value::Str(text)] => Some(("&str".to_string(), format!("{:?}", text))),
+            [Value::Ref(reference)] => {
+                let target = self.tree.resolve(reference)?;
+                Some((format!("&{}::Node", BASE), format!("&{}::NODE", self.module_path(target))))
+            }
+            [Value::Bytes(bytes)] => Some(("&[u8]".to_string(), format!("&{:?}", bytes))),
+            [Value::Bits(bits, nums)] => match nums.as_slice() {
+                [value] => Some((format!("u{}", bits), format!("{:#x}", value))),
+                _ => Some((format!("&[u{}]", bits), format!("&{:?}", nums))),
+            },
+            values if values.iter().all(|v| matches!(v, Value::Str(_))) => {
+                let strs = strings(values)?;
+                Some(("&[&str]".to_string(), format!("&{:?}", strs)))
+            }
+            values if values.iter().all(|v| matches!(v, Value::Cells(_))) => self.cells_value(values),
+            _ => None,
+        }
+    }
+
+    /// Cell lists are either plain numbers, or phandle arrays, where each reference starts a new
+    /// entry with the cells that follow it.
+    fn cells_value(&self, values: &[Value]) -> Option<(String, String)> {
+        let cells: Vec<&Cell> = values
+            .iter()
+            .flat_map(|v| match v {
+                Value::Cells(cells) => cells.iter(),
+                _ => unreachable!(),
+            })
+            .collect();
+
+        match cells.as_slice() {
+            [] => Some(("&[u32]".to_string(), "&[]".to_string())),
+            [Cell::Num(value)] => Some(("u32".to_string(), format!("{:#x}", value))),
+            [Cell::Ref(reference)] => {
+                let target = self.tree.resolve(reference)?;
+                Some((format!("&{}::Node", BASE), format!("&{}::NODE", self.module_path(target))))
+            }
+            [Cell::Num(_), ..] => {
+                let nums = numbers(values)?;
+                Some(("&[u32]".to_string(), format!("&{:?}", nums)))
+            }
+            [Cell::Ref(_), ..] => {
+                let mut entries = Vec::new();
+                for cell in cells {
+                    match cell {
+                        Cell::Ref(reference) => {
+                            let target = self.tree.resolve(reference)?;
+                            entries.push((target, Vec::new()));
+                        }
+                        Cell::Num(value) => entries.last_mut().unwrap().1.push(*value as u32),
+                    }
+                }
+                let entries: Vec<String> = entries
+                    .iter()
+                    .map(|(target, cells)| {
+                        format!(
+                            "{}::PhandleEntry {{ node: &{}::NODE, cells: &{:?} }}",
+                            BASE,
+                            self.module_path(*target),
+                            cells
+                        )
+                    })
+                    .collect();
+                Some((format!("&[{}::PhandleEntry]", BASE), format!("&[{}]", entries.join(", "))))
+            }
+        }
+    }
+
+    /// Split a `reg` property into address/size pairs, based on the parent's cell sizes.
+    fn reg(&self, index: usize, values: &[Value]) -> Option<(String, String)> {
+        let parent = self.tree.nodes[index].parent?;
+        let (addr_cells, size_cells) = self.tree.cell_sizes(parent);
+        let cells = numbers(values)?;
+        let stride = (addr_cells + size_cells) as usize;
+        if addr_cells > 2 || size_cells > 2 || stride == 0 || cells.len() % stride != 0 {
+            return None;
+        }
+
+        let combine = |part: &[u32]| part.iter().fold(0u64, |acc, &c| (acc << 32) | c as u64);
+        let entries: Vec<String> = cells
+            .chunks(stride)
+            .map(|chunk| {
+                let (addr, size) = chunk.split_at(addr_cells as usize);
+                format!("{}::Reg {{ addr: {:#x}, size: {:#x} }}", BASE, combine(addr), combine(size))
+            })
+            .collect();
+        Some((format!("&[{}::Reg]", BASE), format!("&[{}]", entries.join(", "))))
+    }
+}
+
+/// Gather the cells of a property, if they are all plain numbers.
+fn numbers(values: &[Value]) -> Option<Vec<u32>> {
+    let mut result = Vec::new();
+    for value in values {
+        match value {
+            Value::Cells(cells) => {
+                for cell in cells {
+                    match cell {
+                        Cell::Num(n) => result.push(*n as u32),
+                        Cell::Ref(_) => return None,
+                    }
+                }
+            }
+            _ => return None,
+        }
+    }
+    Some(result)
+}
+
+/// Gather the values of a property, if they are all strings.
+fn strings(values: &[Value]) -> Option<Vec<&str>> {
+    values
+        .iter()
+        .map(|v| match v {
+            Value::Str(text) => Some(text.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Compute the module identifier for each node.  Siblings whose names map to the same identifier
+/// are given a numeric suffix.
+fn node_idents(tree: &DeviceTree) -> Vec<String> {
+    let mut idents = vec![String::new(); tree.nodes.len()];
+    for (index, node) in tree.nodes.iter().enumerate() {
+        let mut used: HashSet<String> = HashSet::new();
+        if index == 0 {
+            // The generated `labels` module lives alongside the root's children.
+            used.insert("labels".to_string());
+        }
+        for &child in &node.children {
+            let base = ident(&tree.nodes[child].name, false);
+            let mut name = base.clone();
+            let mut n = 1;
+            while !used.insert(name.clone()) {
+                name = format!("{}_{}", base, n);
+                n += 1;
+            }
+            idents[child] = name;
+        }
+    }
+    idents
+}
+
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl",
+    "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "static", "struct", "super", "trait", "true", "try", "type", "typeof",
+    "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// Convert a devicetree name into a Rust identifier.  Modules use lower case, and constants use
+/// upper case.
+fn ident(name: &str, upper: bool) -> String {
+    let mut result: String = name
+        .trim_start_matches('#')
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if upper {
+        result.make_ascii_uppercase();
+    } else {
+        result.make_ascii_lowercase();
+    }
+    if result.is_empty() || result.starts_with(|c: char| c.is_ascii_digit()) {
+        result.insert(0, '_');
+    }
+    if KEYWORDS.contains(&result.as_str()) {
+        result.push('_');
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::generated;
+
+    fn generate_text(body: &str) -> (String, Vec<String>) {
+        let text = format!("/dts-v1/;\n/ {{\n{}\n}};\n", body);
+        let tree = DeviceTree::parse(&text).unwrap();
+        generated(|out| generate(&tree, out))
+    }
+
+    #[test]
+    fn properties() {
+        let (text, warnings) = generate_text(
+            "node {\n\
+             \tstatus = \"okay\";\n\
+             \tread-only;\n\
+             \tnum = <0x10>;\n\
+             \tlist = <1 2>;\n\
+             \tbytes = [01 02];\n\
+             \tsmall = /bits/ 8 <1 2>;\n\
+             \tbig = /bits/ 64 <0x100000000>;\n\
+             };",
+        );
+        assert!(warnings.is_empty());
+        assert!(text.contains("pub const STATUS: &str = \"okay\";"));
+        assert!(text.contains("pub const READ_ONLY: bool = true;"));
+        assert!(text.contains("pub const NUM: u32 = 0x10;"));
+        assert!(text.contains("pub const LIST: &[u32] = &[1, 2];"));
+        assert!(text.contains("pub const BYTES: &[u8] = &[1, 2];"));
+        assert!(text.contains("pub const SMALL: &[u8] = &[1, 2];"));
+        assert!(text.contains("pub const BIG: u64 = 0x100000000;"));
+    }
+
+    #[test]
+    fn phandles() {
+        let (text, _) = generate_text(
+            "gpio0: gpio {};\n\
+             leds {\n\
+             \tgpios = <&gpio0 3 1>, <&gpio0 4 0>;\n\
+             \tparent = <&gpio0>;\n\
+             };",
+        );
+        assert!(text.contains(
+            "pub const GPIOS: &[crate::devicetree::PhandleEntry] = &[\
+             crate::devicetree::PhandleEntry { node: &crate::devicetree::gpio::NODE, \
+             cells: &[3, 1] }, \
+             crate::devicetree::PhandleEntry { node: &crate::devicetree::gpio::NODE, \
+             cells: &[4, 0] }];"
+        ));
+        assert!(text.contains(
+            "pub const PARENT: &crate::devicetree::Node = &crate::devicetree::gpio::NODE;"
+        ));
+    }
+
+    #[test]
+    fn labels() {
+        let (text, warnings) = generate_text("uart: a {};\nUART: b {};\nother: c {};");
+        assert!(text.contains("pub use crate::devicetree::a as uart;"));
+        assert!(!text.contains("pub use crate::devicetree::b as uart;"));
+        assert!(text.contains("pub use crate::devicetree::c as other;"));
+        assert_eq!(warnings, ["label \"UART\" conflicts with label \"uart\""]);
+    }
+
+    #[test]
+    fn devices() {
+        let mut tree = DeviceTree::parse("/dts-v1/;\n/ {\n\tuart {};\n\tother {};\n};\n").unwrap();
+        tree.set_ordinals(
+            "#define DT_N_S_uart_PATH \"/uart\"\n\
+             #define DT_N_S_uart_ORD 7\n",
+        );
+        let mut out = Vec::new();
+        generate(&tree, &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.contains(
+            "pub mod uart {\n    device_ref!(device, 7);\n    \
+             pub const NODE: crate::devicetree::Node = crate::devicetree::Node {\n        \
+             path: \"/uart\",\n        name: \"uart\",\n        device: Some(device),\n"
+        ));
+        assert!(text.contains(
+            "path: \"/other\",\n        name: \"other\",\n        device: None,\n"
+        ));
+    }
+
+    #[test]
+    fn sibling_names() {
+        let (text, _) = generate_text("uart@1 {};\nuart-1 {};\nlabels {};");
+        assert!(text.contains("pub mod uart_1 {"));
+        assert!(text.contains("pub mod uart_1_1 {"));
+        assert!(text.contains("pub mod labels_1 {"));
+    }
+}
@@ -0,0 +1,526 @@
+// Copyright (c) 2024 Linaro LTD
+// SPDX-License-Identifier: Apache-2.0
+
+//! Parser for the final `zephyr.dts`.
+//!
+//! The file handed to us by the Zephyr build has already been run through the C preprocessor and
+//! through dtlib, so there are no includes, no macros, no `/delete-node/` directives and no label
+//! references outside of the tree.  This parser only handles that reduced form, and will return an
+//! error on anything else, rather than trying to be a general dtc replacement.
+
+use super::{Cell, Node, Property, Ref, Value};
+use crate::parse_error::{ParseError, Result};
+
+/// Parse the text of a dts file into the arena of nodes.  The root is always at index 0.
+pub fn parse(text: &str) -> Result<Vec<Node>> {
+    let mut parser = Parser {
+        text: text.as_bytes(),
+        pos: 0,
+        nodes: vec![Node::new("/".to_string(), "/".to_string(), None)],
+    };
+    parser.parse_file()?;
+    Ok(parser.nodes)
+}
+
+struct Parser<'a> {
+    text: &'a [u8],
+    pos: usize,
+    nodes: Vec<Node>,
+}
+
+/// Characters allowed in node and property names.
+fn is_name_char(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || b",._+*#?@-".contains(&c)
+}
+
+/// Characters allowed in labels.
+fn is_label_char(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || c == b'_'
+}
+
+impl<'a> Parser<'a> {
+    fn error<T>(&self, message: impl Into<String>) -> Result<T> {
+        let line = 1 + self.text[..self.pos].iter().filter(|&&c| c == b'\n').count();
+        Err(ParseError { line, message: message.into() })
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.get(self.pos).copied()
+    }
+
+    /// Skip over whitespace and comments.
+    fn skip_ws(&mut self) {
+        loop {
+            match self.peek() {
+                Some(c) if c.is_ascii_whitespace() => self.pos += 1,
+                Some(b'/') if self.text.get(self.pos + 1) == Some(&b'*') => {
+                    self.pos += 2;
+                    while self.pos < self.text.len() && !self.text[self.pos..].starts_with(b"*/") {
+                        self.pos += 1;
+                    }
+                    self.pos = (self.pos + 2).min(self.text.len());
+                }
+                Some(b'/') if self.text.get(self.pos + 1) == Some(&b'/') => {
+                    while !matches!(self.peek(), None | Some(b'\n')) {
+                        self.pos += 1;
+                    }
+                }
+                _ => break,
+            }
+        }
+    }
+
+    /// Consume the given punctuation, after skipping whitespace.
+    fn expect(&mut self, c: u8) -> Result<()> {
+        self.skip_ws();
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            self.error(format!("expecting '{}'", c as char))
+        }
+    }
+
+    /// Consume the punctuation if present.
+    fn accept(&mut self, c: u8) -> bool {
+        self.skip_ws();
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn take_while(&mut self, pred: impl Fn(u8) -> bool) -> &'a str {
+        let start = self.pos;
+        while matches!(self.peek(), Some(c) if pred(c)) {
+            self.pos += 1;
+        }
+        // The predicates only accept ASCII, so this is always valid utf-8.
+        std::str::from_utf8(&self.text[start..self.pos]).unwrap()
+    }
+
+    /// Read a `/directive/`, returning the text between the slashes.
+    fn directive(&mut self) -> Result<&'a str> {
+        self.expect(b'/')?;
+        let name = self.take_while(|c| c.is_ascii_alphanumeric() || c == b'-');
+        if self.peek() != Some(b'/') {
+            return self.error("malformed directive");
+        }
+        self.pos += 1;
+        Ok(name)
+    }
+
+    fn parse_file(&mut self) -> Result<()> {
+        self.skip_ws();
+        if self.directive()? != "dts-v1" {
+            return self.error("expecting /dts-v1/");
+        }
+        self.expect(b';')?;
+
+        loop {
+            self.skip_ws();
+            match self.peek() {
+                None => return Ok(()),
+                Some(b'/') => {
+                    // Either the root node, or another directive.
+                    if self.text.get(self.pos + 1).is_some_and(|c| c.is_ascii_whitespace() || *c == b'{') {
+                        self.pos += 1;
+                        self.expect(b'{')?;
+                        self.parse_body(0)?;
+                        self.expect(b'}')?;
+                        self.expect(b';')?;
+                    } else {
+                        match self.directive()? {
+                            "memreserve" => {
+                                self.skip_ws();
+                                self.number()?;
+                                self.skip_ws();
+                                self.number()?;
+                                self.expect(b';')?;
+                            }
+                            other => return self.error(format!("unsupported directive /{}/", other)),
+                        }
+                    }
+                }
+                Some(_) => return self.error("expecting root node"),
+            }
+        }
+    }
+
+    /// Parse the properties and children of the node at `index`, up to, but not including the
+    /// closing brace.
+    fn parse_body(&mut self, index: usize) -> Result<()> {
+        loop {
+            self.skip_ws();
+            match self.peek() {
+                Some(b'}') => return Ok(()),
+                None => return self.error("unexpected end of file"),
+                _ => (),
+            }
+
+            // Gather any labels.  A label and a name are distinguished by the trailing ':'.
+            let mut labels = Vec::new();
+            let name = loop {
+                self.skip_ws();
+                let word = self.take_while(is_name_char);
+                if word.is_empty() {
+                    return self.error("expecting property or node name");
+                }
+                if self.peek() == Some(b':') && word.bytes().all(is_label_char) {
+                    self.pos += 1;
+                    labels.push(word.to_string());
+                } else {
+                    break word;
+                }
+            };
+
+            self.skip_ws();
+            match self.peek() {
+                Some(b'{') => {
+                    self.pos += 1;
+                    let parent_path = &self.nodes[index].path;
+                    let path = if parent_path == "/" {
+                        format!("/{}", name)
+                    } else {
+                        format!("{}/{}", parent_path, name)
+                    };
+                    let mut node = Node::new(name.to_string(), path, Some(index));
+                    node.labels = labels;
+                    let child = self.nodes.len();
+                    self.nodes.push(node);
+                    self.nodes[index].children.push(child);
+                    self.parse_body(child)?;
+                    self.expect(b'}')?;
+                    self.expect(b';')?;
+                }
+                Some(b'=') => {
+                    self.pos += 1;
+                    let mut values = vec![self.value()?];
+                    while self.accept(b',') {
+                        values.push(self.value()?);
+                    }
+                    self.expect(b';')?;
+                    self.nodes[index].props.push(Property { name: name.to_string(), values });
+                }
+                Some(b';') => {
+                    self.pos += 1;
+                    self.nodes[index].props.push(Property { name: name.to_string(), values: vec![] });
+                }
+                _ => return self.error(format!("unexpected character after {:?}", name)),
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<Value> {
+        self.skip_ws();
+        match self.peek() {
+            Some(b'"') => Ok(Value::Str(self.string()?)),
+            Some(b'<') => {
+                self.pos += 1;
+                Ok(Value::Cells(self.cells(32)?))
+            }
+            Some(b'[') => {
+                self.pos += 1;
+                Ok(Value::Bytes(self.bytes()?))
+            }
+            Some(b'&') => Ok(Value::Ref(self.reference()?)),
+            Some(b'/') => {
+                if self.directive()? != "bits" {
+                    return self.error("expecting /bits/");
+                }
+                self.skip_ws();
+                let bits = self.number()?;
+                if ![8, 16, 32, 64].contains(&bits) {
+                    return self.error(format!("invalid /bits/ {}", bits));
+                }
+                self.expect(b'<')?;
+                let cells = self.cells(bits as u32)?;
+                if bits == 32 {
+                    return Ok(Value::Cells(cells));
+                }
+                // Only 32-bit cells can hold phandles, so these are all numbers.
+                let values = cells
+                    .into_iter()
+                    .map(|cell| match cell {
+                        Cell::Num(value) => value,
+                        Cell::Ref(_) => unreachable!(),
+                    })
+                    .collect();
+                Ok(Value::Bits(bits as u32, values))
+            }
+            _ => self.error("expecting property value"),
+        }
+    }
+
+    /// Parse a cell list of `bits` sized elements, after the opening '<', consuming the closing
+    /// '>'.  Numbers that don't fit are rejected, rather than truncated.
+    fn cells(&mut self, bits: u32) -> Result<Vec<Cell>> {
+        let max = u64::MAX >> (64 - bits);
+        let mut cells = Vec::new();
+        loop {
+            self.skip_ws();
+            match self.peek() {
+                Some(b'>') => {
+                    self.pos += 1;
+                    return Ok(cells);
+                }
+                Some(b'&') if bits != 32 => {
+                    return self.error("phandles are only allowed in 32-bit cells");
+                }
+                Some(b'&') => cells.push(Cell::Ref(self.reference()?)),
+                Some(b'\'') => {
+                    self.pos += 1;
+                    let c = self.escaped_char()?;
+                    if self.peek() != Some(b'\'') {
+                        return self.error("unterminated character literal");
+                    }
+                    self.pos += 1;
+                    cells.push(Cell::Num(c as u64));
+                }
+                Some(c) if c.is_ascii_digit() => {
+                    let value = self.number()?;
+                    if value > max {
+                        return self.error(format!("{:#x} does not fit in {} bits", value, bits));
+                    }
+                    cells.push(Cell::Num(value));
+                }
+                Some(b'(') => return self.error("expressions are not supported in cells"),
+                _ => return self.error("unexpected character in cell list"),
+            }
+        }
+    }
+
+    /// Parse a bytestring, after the opening '[', consuming the closing ']'.
+    fn bytes(&mut self) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        loop {
+            self.skip_ws();
+            if self.peek() == Some(b']') {
+                self.pos += 1;
+                return Ok(bytes);
+            }
+            let digits = self.take_while(|c| c.is_ascii_hexdigit());
+            if digits.is_empty() || !digits.len().is_multiple_of(2) {
+                return self.error("malformed bytestring");
+            }
+            for i in (0..digits.len()).step_by(2) {
+                bytes.push(u8::from_str_radix(&digits[i..i + 2], 16).unwrap());
+            }
+        }
+    }
+
+    /// Parse a reference, either `&label` or `&{/path}`.
+    fn reference(&mut self) -> Result<Ref> {
+        self.expect(b'&')?;
+        if self.peek() == Some(b'{') {
+            self.pos += 1;
+            let path = self.take_while(|c| c != b'}');
+            let path = path.to_string();
+            self.expect(b'}')?;
+            Ok(Ref::Path(path))
+        } else {
+            let label = self.take_while(is_label_char);
+            if label.is_empty() {
+                return self.error("expecting label after '&'");
+            }
+            Ok(Ref::Label(label.to_string()))
+        }
+    }
+
+    fn number(&mut self) -> Result<u64> {
+        let text = self.take_while(|c| c.is_ascii_alphanumeric());
+        // Drop any C integer suffixes.
+        let text = text.trim_end_matches(['u', 'U', 'l', 'L']);
+        let value = if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+            u64::from_str_radix(hex, 16)
+        } else if text.len() > 1 && text.starts_with('0') {
+            u64::from_str_radix(&text[1..], 8)
+        } else {
+            text.parse()
+        };
+        match value {
+            Ok(value) => Ok(value),
+            Err(_) => self.error(format!("invalid number {:?}", text)),
+        }
+    }
+
+    fn string(&mut self) -> Result<String> {
+        self.expect(b'"')?;
+        let mut buf = Vec::new();
+        loop {
+            match self.peek() {
+                None => return self.error("unterminated string"),
+                Some(b'"') => {
+                    self.pos += 1;
+                    break;
+                }
+                Some(_) => buf.push(self.escaped_char()?),
+            }
+        }
+        match String::from_utf8(buf) {
+            Ok(s) => Ok(s),
+            Err(_) => self.error("string is not valid utf-8"),
+        }
+    }
+
+    /// Read a single, possibly escaped, byte from a string or character literal.
+    fn escaped_char(&mut self) -> Result<u8> {
+        let c = match self.peek() {
+            Some(c) => c,
+            None => return self.error("unexpected end of file"),
+        };
+        self.pos += 1;
+        if c != b'\\' {
+            return Ok(c);
+        }
+        let c = match self.peek() {
+            Some(c) => c,
+            None => return self.error("unexpected end of file"),
+        };
+        self.pos += 1;
+        Ok(match c {
+            b'a' => 0x07,
+            b'b' => 0x08,
+            b't' => b'\t',
+            b'n' => b'\n',
+            b'v' => 0x0b,
+            b'f' => 0x0c,
+            b'r' => b'\r',
+            b'x' => {
+                let start = self.pos;
+                while self.pos < start + 2 && matches!(self.peek(), Some(c) if c.is_ascii_hexdigit()) {
+                    self.pos += 1;
+                }
+                let digits = std::str::from_utf8(&self.text[start..self.pos]).unwrap();
+                match u8::from_str_radix(digits, 16) {
+                    Ok(value) => value,
+                    Err(_) => return self.error("malformed \\x escape"),
+                }
+            }
+            b'0'..=b'7' => {
+                let start = self.pos - 1;
+                while self.pos < start + 3 && matches!(self.peek(), Some(b'0'..=b'7')) {
+                    self.pos += 1;
+                }
+                let digits = std::str::from_utf8(&self.text[start..self.pos]).unwrap();
+                match u8::from_str_radix(digits, 8) {
+                    Ok(value) => value,
+                    Err(_) => return self.error("malformed octal escape"),
+                }
+            }
+            other => other,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::error_line;
+
+    fn props(text: &str) -> Vec<Property> {
+        let text = format!("/dts-v1/;\n/ {{\n{}\n}};\n", text);
+        let mut nodes = parse(&text).unwrap();
+        nodes.swap_remove(0).props
+    }
+
+    #[test]
+    fn cells() {
+        let props = props("a = <1 0x20 010 'A'>, <0xffffffff>;");
+        match props[0].values.as_slice() {
+            [Value::Cells(first), Value::Cells(second)] => {
+                let nums: Vec<u64> = first
+                    .iter()
+                    .chain(second)
+                    .map(|cell| match cell {
+                        Cell::Num(n) => *n,
+                        Cell::Ref(_) => panic!("unexpected reference"),
+                    })
+                    .collect();
+                assert_eq!(nums, [1, 0x20, 8, 65, 0xffff_ffff]);
+            }
+            other => panic!("unexpected value {:?}", other),
+        }
+    }
+
+    #[test]
+    fn cell_out_of_range() {
+        assert_eq!(error_line(parse("/dts-v1/;\n/ {\n\ta = <0x100000000>;\n};\n")), 3);
+    }
+
+    #[test]
+    fn strings() {
+        let props = props(r#"a = "one", "t\"w\\o\x41\101\n";"#);
+        match props[0].values.as_slice() {
+            [Value::Str(one), Value::Str(two)] => {
+                assert_eq!(one, "one");
+                assert_eq!(two, "t\"w\\oAA\n");
+            }
+            other => panic!("unexpected value {:?}", other),
+        }
+    }
+
+    #[test]
+    fn phandles() {
+        let props = props("a = <&gpio0 3 0>;\nb = &{/soc/uart@1000};");
+        match props[0].values.as_slice() {
+            [Value::Cells(cells)] => match cells.as_slice() {
+                [Cell::Ref(Ref::Label(label)), Cell::Num(3), Cell::Num(0)] => {
+                    assert_eq!(label, "gpio0");
+                }
+                other => panic!("unexpected cells {:?}", other),
+            },
+            other => panic!("unexpected value {:?}", other),
+        }
+        assert!(matches!(
+            props[1].values.as_slice(),
+            [Value::Ref(Ref::Path(path))] if path == "/soc/uart@1000"
+        ));
+    }
+
+    #[test]
+    fn bits() {
+        let props = props(
+            "a = /bits/ 8 <1 0xff>;\n\
+             b = /bits/ 16 <0xffff>;\n\
+             c = /bits/ 32 <7>;\n\
+             d = /bits/ 64 <0x100000000>;",
+        );
+        assert!(matches!(props[0].values.as_slice(), [Value::Bits(8, v)] if v == &[1, 0xff]));
+        assert!(matches!(props[1].values.as_slice(), [Value::Bits(16, v)] if v == &[0xffff]));
+        assert!(matches!(props[2].values.as_slice(), [Value::Cells(c)] if c.len() == 1));
+        assert!(matches!(
+            props[3].values.as_slice(),
+            [Value::Bits(64, v)] if v == &[0x1_0000_0000]
+        ));
+    }
+
+    #[test]
+    fn bits_errors() {
+        assert_eq!(error_line(parse("/dts-v1/;\n/ {\n\ta = /bits/ 8 <0x100>;\n};\n")), 3);
+        assert_eq!(error_line(parse("/dts-v1/;\n/ {\n\n\ta = /bits/ 12 <1>;\n};\n")), 4);
+        assert_eq!(error_line(parse("/dts-v1/;\n/ {\n\ta = /bits/ 16 <&x>;\n};\n")), 3);
+    }
+
+    #[test]
+    fn labels() {
+        let text = "/dts-v1/;\n/ {\n\tl1: l2: node@0 {\n\t\tchild {};\n\t};\n};\n";
+        let nodes = parse(text).unwrap();
+        assert_eq!(nodes[1].labels, ["l1", "l2"]);
+        assert_eq!(nodes[1].path, "/node@0");
+        assert_eq!(nodes[2].path, "/node@0/child");
+        assert_eq!(nodes[2].parent, Some(1));
+        assert!(nodes[2].labels.is_empty());
+    }
+
+    #[test]
+    fn error_lines() {
+        assert_eq!(error_line(parse("/ { };\n")), 1);
+        assert_eq!(error_line(parse("/dts-v1/;\n/ {\n\ta = <1\n};\n")), 4);
+        assert_eq!(error_line(parse("/dts-v1/;\n/ {\n\ta = \"open;\n};\n")), 5);
+        assert_eq!(error_line(parse("/dts-v1/;\n/* a\ncomment */\n/ {\n\ta = (1);\n};\n")), 5);
+        assert_eq!(error_line(parse("/dts-v1/;\n/ {\n\ta = <(1 + 2)>;\n};\n")), 3);
+    }
+}
@@ -0,0 +1,143 @@
+// Copyright (c) 2024 Linaro LTD
+// SPDX-License-Identifier: Apache-2.0
+
+//! Kconfig support.
+//!
+//! The .config only holds the values of the Kconfig symbols, not their types, and leaves out
+//! disabled symbols that aren't visible.  Instead, `rust_cargo_application()` has the Kconfig tools
+//! describe every symbol, with its type, value and range, along with the named choices, in the file
+//! given by `KCONFIG_SYMBOLS`.  This module reads that file, and generates the `kconfig` module
+//! from it.
+
+use std::env;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+mod output;
+mod parse;
+
+pub use crate::parse_error::ParseError;
+
+/// A Kconfig symbol, and its value.
+#[derive(Debug)]
+pub struct Symbol {
+    /// The name, including the `CONFIG_` prefix.
+    pub name: String,
+    pub value: Value,
+    /// The prompt, which is empty for symbols without one.
+    pub prompt: String,
+}
+
+/// The value of a symbol, according to its type.
+#[derive(Debug)]
+pub enum Value {
+    /// A bool or tristate symbol.  Zephyr has no loadable modules, so a tristate of `m` is false.
+    Bool(bool),
+    /// An int symbol, along with its range, if it has one.
+    Int { value: i64, range: Option<(i64, i64)> },
+    /// A hex symbol, along with its range, if it has one.
+    Hex { value: u64, range: Option<(u64, u64)> },
+    /// A string symbol, unescaped.
+    Str(String),
+}
+
+/// A named Kconfig choice.
+#[derive(Debug)]
+pub struct Choice {
+    /// The name, including the `CONFIG_` prefix.
+    pub name: String,
+    /// The names of the symbols that can be chosen.
+    pub symbols: Vec<String>,
+    /// The symbol that is selected, if any.
+    pub selection: Option<String>,
+    /// The prompt, which is empty for choices without one.
+    pub prompt: String,
+}
+
+/// The described symbols and choices.
+#[derive(Debug, Default)]
+pub struct Symbols {
+    pub symbols: Vec<Symbol>,
+    pub choices: Vec<Choice>,
+}
+
+impl Symbols {
+    /// Parse the text of a `KCONFIG_SYMBOLS` file.
+    pub fn parse(text: &str) -> Result<Symbols, ParseError> {
+        parse::parse(text)
+    }
+
+    /// Look up a symbol by name.
+    pub fn get(&self, name: &str) -> Option<&Symbol> {
+        self.symbols.iter().find(|sym| sym.name == name)
+    }
+}
+
+/// The errors from generating the `kconfig` module.
+#[derive(Debug)]
+pub enum KconfigError {
+    /// A needed environment variable is not set.  `KCONFIG_SYMBOLS` is only set when building
+    /// through `rust_cargo_application()`, and `OUT_DIR` when running as a build script.
+    NotSet(&'static str),
+    /// The symbols could not be read, or the module could not be written.
+    Io { path: PathBuf, error: io::Error },
+    /// The symbols file could not be understood.
+    Parse { path: PathBuf, error: ParseError },
+}
+
+impl fmt::Display for KconfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KconfigError::NotSet(name) => write!(
+                f,
+                "{} is not set: the kconfig module can only be generated by a build script run \
+                 under rust_cargo_application()",
+                name
+            ),
+            KconfigError::Io { path, error } => write!(f, "{}: {}", path.display(), error),
+            KconfigError::Parse { path, error } => write!(f, "{}: {}", path.display(), error),
+        }
+    }
+}
+
+impl std::error::Error for KconfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            KconfigError::NotSet(_) => None,
+            KconfigError::Io { error, .. } => Some(error),
+            KconfigError::Parse { error, .. } => Some(error),
+        }
+    }
+}
+
+/// Capture the Kconfig values in a `kconfig` module, as constants typed according to the Kconfig
+/// symbols.  Bool symbols are `bool`, enabled or not, int symbols are `u32` if their range allows,
+/// and `i64` otherwise, hex symbols are `u32`, or `u64` if their range or value doesn't fit, and
+/// strings are `&str`.  Named choices become an enum, and an `Option` constant with the selection.
+///
+/// The symbols are read from the file given by `KCONFIG_SYMBOLS`, which `rust_cargo_application()`
+/// sets, and the module is written to `kconfig.rs` in `OUT_DIR`.
+pub fn build_kconfig_mod() -> Result<(), KconfigError> {
+    println!("cargo:rerun-if-env-changed=KCONFIG_SYMBOLS");
+    let symbols_path = env::var_os("KCONFIG_SYMBOLS")
+        .map(PathBuf::from)
+        .ok_or(KconfigError::NotSet("KCONFIG_SYMBOLS"))?;
+    let outdir = env::var_os("OUT_DIR").ok_or(KconfigError::NotSet("OUT_DIR"))?;
+
+    println!("cargo:rerun-if-changed={}", symbols_path.display());
+
+    let text = fs::read_to_string(&symbols_path)
+        .map_err(|error| KconfigError::Io { path: symbols_path.clone(), error })?;
+    let symbols = Symbols::parse(&text)
+        .map_err(|error| KconfigError::Parse { path: symbols_path, error })?;
+
+    let gen_path = Path::new(&outdir).join("kconfig.rs");
+    File::create(&gen_path)
+        .and_then(|mut f| {
+            output::generate(&symbols, &mut f)?;
+            f.flush()
+        })
+        .map_err(|error| KconfigError::Io { path: gen_path, error })
+}
@@ -0,0 +1,245 @@
+// Copyright (c) 2024 Linaro LTD
+// SPDX-License-Identifier: Apache-2.0
+
+//! Rust code generation for the Kconfig settings.
+//!
+//! Every symbol becomes a constant in the `kconfig` module, with the same name, and a type based
+//! on the type of the symbol.  Each named choice becomes an enum, with a variant for each of its
+//! symbols, along with an `Option` constant, named after the choice, giving the selection.
+//! Names that only differ in their underscores would give the same enum or variant name, so later
+//! ones have a number added.
+
+use std::collections::HashSet;
+use std::io::{Result, Write};
+
+use super::{Choice, Symbol, Symbols, Value};
+
+pub fn generate(symbols: &Symbols, w: &mut impl Write) -> Result<()> {
+    writeln!(w, "// Generated Kconfig settings for the current build.  Do not edit.")?;
+    writeln!(w)?;
+    writeln!(w, "/// The Kconfig settings.")?;
+    writeln!(w, "pub mod kconfig {{")?;
+
+    for sym in &symbols.symbols {
+        symbol(sym, w)?;
+    }
+
+    let names: HashSet<&str> = symbols.symbols.iter().map(|sym| sym.name.as_str()).collect();
+    // Choices without symbols are skipped.
+    let choices: Vec<&Choice> =
+        symbols.choices.iter().filter(|ch| !ch.symbols.is_empty()).collect();
+    let types = camel_case_names(choices.iter().map(|ch| ch.name.as_str()));
+    for (ch, ty) in choices.into_iter().zip(types) {
+        choice(ch, &ty, names.contains(ch.name.as_str()), w)?;
+    }
+
+    writeln!(w, "}}")
+}
+
+fn symbol(sym: &Symbol, w: &mut impl Write) -> Result<()> {
+    doc(&sym.prompt, w)?;
+    match &sym.value {
+        Value::Bool(value) => {
+            writeln!(w, "    pub const {}: bool = {};", sym.name, value)
+        }
+        Value::Int { value, range } => {
+            let fits = matches!(range, Some((low, high)) if *low >= 0 && *high <= u32::MAX as i64);
+            let text = range.map(|(low, high)| (low.to_string(), high.to_string()));
+            range_doc(&sym.prompt, text, w)?;
+            let ty = if fits { "u32" } else { "i64" };
+            writeln!(w, "    pub const {}: {} = {};", sym.name, ty, value)
+        }
+        Value::Hex { value, range } => {
+            let max = range.map_or(*value, |(_, high)| high.max(*value));
+            let text = range.map(|(low, high)| (format!("{:#x}", low), format!("{:#x}", high)));
+            range_doc(&sym.prompt, text, w)?;
+            let ty = if max <= u32::MAX as u64 { "u32" } else { "u64" };
+            writeln!(w, "    pub const {}: {} = {:#x};", sym.name, ty, value)
+        }
+        Value::Str(value) => {
+            writeln!(w, "    pub const {}: &str = {:?};", sym.name, value)
+        }
+    }
+}
+
+/// A choice with symbols, as the enum `ty`.  The constant is left out if a symbol has the same
+/// name, which Kconfig allows.
+fn choice(ch: &Choice, ty: &str, name_taken: bool, w: &mut impl Write) -> Result<()> {
+    let variants = camel_case_names(ch.symbols.iter().map(String::as_str));
+
+    writeln!(w)?;
+    doc(&ch.prompt, w)?;
+    writeln!(w, "    #[derive(Clone, Copy, Debug, PartialEq, Eq)]")?;
+    writeln!(w, "    pub enum {} {{", ty)?;
+    for (sym, variant) in ch.symbols.iter().zip(&variants) {
+        writeln!(w, "        /// `{}`", sym)?;
+        writeln!(w, "        {},", variant)?;
+    }
+    writeln!(w, "    }}")?;
+
+    if !name_taken {
+        let selected =
+            ch.selection.as_ref().and_then(|sel| ch.symbols.iter().position(|sym| sym == sel));
+        let selection = match selected {
+            Some(index) => format!("Some({}::{})", ty, variants[index]),
+            None => "None".to_string(),
+        };
+        writeln!(w, "    /// The selection of [`{}`], if any.", ty)?;
+        writeln!(w, "    pub const {}: Option<{}> = {};", ch.name, ty, selection)?;
+    }
+    Ok(())
+}
+
+/// The prompt, as the doc comment of a symbol or choice.
+fn doc(prompt: &str, w: &mut impl Write) -> Result<()> {
+    for line in prompt.lines() {
+        writeln!(w, "    /// {}", line.trim())?;
+    }
+    Ok(())
+}
+
+/// The range of a symbol, added to the doc comment after the prompt.
+fn range_doc(prompt: &str, range: Option<(String, String)>, w: &mut impl Write) -> Result<()> {
+    if let Some((low, high)) = range {
+        if !prompt.is_empty() {
+            writeln!(w, "    ///")?;
+        }
+        writeln!(w, "    /// Range: `{}..={}`", low, high)?;
+    }
+    Ok(())
+}
+
+/// The `camel_case` names for a list of symbol or choice names, with a number added to any that
+/// would otherwise be the same as an earlier one.
+fn camel_case_names<'a>(names: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let mut used = HashSet::new();
+    names
+        .map(|name| {
+            let base = camel_case(name);
+            let mut result = base.clone();
+            let mut n = 1;
+            while !used.insert(result.clone()) {
+                result = format!("{}{}", base, n);
+                n += 1;
+            }
+            result
+        })
+        .collect()
+}
+
+/// The Rust type or variant name for a symbol or choice name: `CONFIG_MINIMAL_LIBC` becomes
+/// `MinimalLibc`.
+fn camel_case(name: &str) -> String {
+    let name = name.strip_prefix("CONFIG_").unwrap_or(name);
+    let mut result = String::new();
+    for word in name.split('_').filter(|word| !word.is_empty()) {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            result.push(first.to_ascii_uppercase());
+            result.extend(chars.map(|c| c.to_ascii_lowercase()));
+        }
+    }
+    if result.is_empty() || result.starts_with(|c: char| c.is_ascii_digit()) {
+        result.insert(0, '_');
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::generated;
+
+    fn generate_text(text: &str) -> String {
+        let symbols = Symbols::parse(text).unwrap();
+        generated(|out| generate(&symbols, out)).0
+    }
+
+    #[test]
+    fn bools() {
+        let text = generate_text("bool\tCONFIG_YES\ty\tEnabled\nbool\tCONFIG_NO\tn\t\n");
+        assert!(text.contains("    /// Enabled\n    pub const CONFIG_YES: bool = true;\n"));
+        assert!(text.contains("pub const CONFIG_NO: bool = false;"));
+    }
+
+    #[test]
+    fn ints() {
+        let text = generate_text(
+            "int\tCONFIG_PLAIN\t5\t\t\t\n\
+             int\tCONFIG_RANGED\t10\t0\t100\tRanged\n\
+             int\tCONFIG_NEGATIVE\t-3\t-10\t10\t\n\
+             int\tCONFIG_LARGE\t5000000000\t0\t10000000000\t\n\
+             int\tCONFIG_MAX\t1\t0\t4294967295\t\n",
+        );
+        assert!(text.contains("pub const CONFIG_PLAIN: i64 = 5;"));
+        assert!(text.contains(
+            "    /// Ranged\n    ///\n    /// Range: `0..=100`\n    \
+             pub const CONFIG_RANGED: u32 = 10;\n"
+        ));
+        assert!(text.contains(
+            "    /// Range: `-10..=10`\n    pub const CONFIG_NEGATIVE: i64 = -3;\n"
+        ));
+        assert!(text.contains("pub const CONFIG_LARGE: i64 = 5000000000;"));
+        assert!(text.contains("pub const CONFIG_MAX: u32 = 1;"));
+    }
+
+    #[test]
+    fn hexes() {
+        let text = generate_text(
+            "hex\tCONFIG_PLAIN\t0x10\t\t\t\n\
+             hex\tCONFIG_BIG_VALUE\t0x100000000\t\t\t\n\
+             hex\tCONFIG_BIG_RANGE\t0x10\t0x0\t0x100000000\t\n\
+             hex\tCONFIG_RANGED\t0x20\t0x10\t0xffffffff\t\n",
+        );
+        assert!(text.contains("pub const CONFIG_PLAIN: u32 = 0x10;"));
+        assert!(text.contains("pub const CONFIG_BIG_VALUE: u64 = 0x100000000;"));
+        assert!(text.contains(
+            "    /// Range: `0x0..=0x100000000`\n    pub const CONFIG_BIG_RANGE: u64 = 0x10;\n"
+        ));
+        assert!(text.contains("pub const CONFIG_RANGED: u32 = 0x20;"));
+    }
+
+    #[test]
+    fn strings() {
+        let text = generate_text("string\tCONFIG_STR\tsay \"hi\"\\\\\\tthere\\n\t\n");
+        assert!(text.contains(r#"pub const CONFIG_STR: &str = "say \"hi\"\\\tthere\n";"#));
+    }
+
+    #[test]
+    fn choices() {
+        let text = generate_text(
+            "choice\tCONFIG_MODE\tCONFIG_MODE_FAST\tCONFIG_MODE_SLOW CONFIG_MODE_FAST\tMode\n\
+             choice\tCONFIG_EMPTY\t\t\t\n\
+             choice\tCONFIG_UNSET\t\tCONFIG_A\t\n",
+        );
+        assert!(text.contains(
+            "    /// Mode\n    #[derive(Clone, Copy, Debug, PartialEq, Eq)]\n    pub enum Mode {\n"
+        ));
+        assert!(text.contains("        /// `CONFIG_MODE_SLOW`\n        ModeSlow,\n"));
+        assert!(text.contains("pub const CONFIG_MODE: Option<Mode> = Some(Mode::ModeFast);"));
+        assert!(!text.contains("Empty"));
+        assert!(text.contains("pub const CONFIG_UNSET: Option<Unset> = None;"));
+    }
+
+    #[test]
+    fn choice_name_taken() {
+        let text = generate_text("bool\tCONFIG_MODE\ty\t\nchoice\tCONFIG_MODE\t\tCONFIG_A\t\n");
+        assert!(text.contains("pub enum Mode {"));
+        assert!(!text.contains("Option<Mode>"));
+    }
+
+    #[test]
+    fn camel_case_collisions() {
+        let text = generate_text(
+            "choice\tCONFIG_FOO_BAR\tCONFIG_B__C\tCONFIG_B_C CONFIG_B__C CONFIG_9\t\n\
+             choice\tCONFIG_FOO__BAR\t\tCONFIG_X\t\n",
+        );
+        assert!(text.contains("pub enum FooBar {"));
+        assert!(text.contains("pub enum FooBar1 {"));
+        assert!(text.contains("        /// `CONFIG_B_C`\n        BC,\n"));
+        assert!(text.contains("        /// `CONFIG_B__C`\n        BC1,\n"));
+        assert!(text.contains("        /// `CONFIG_9`\n        _9,\n"));
+        assert!(text.contains("pub const CONFIG_FOO_BAR: Option<FooBar> = Some(FooBar::BC1);"));
+        assert!(text.contains("pub const CONFIG_FOO__BAR: Option<FooBar1> = None;"));
+    }
+}
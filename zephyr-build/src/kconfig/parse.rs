@@ -0,0 +1,216 @@
+// Copyright (c) 2024 Linaro LTD
+// SPDX-License-Identifier: Apache-2.0
+
+//! Parser for the `KCONFIG_SYMBOLS` file.
+//!
+//! This is written by `scripts/rust_kconfig.py`, which documents the format.  Each line is a
+//! symbol or choice, with tab separated fields, where backslashes, tabs and newlines within a field
+//! are escaped.
+
+use super::{Choice, Symbol, Symbols, Value};
+use crate::parse_error::{ParseError, Result};
+
+pub fn parse(text: &str) -> Result<Symbols> {
+    let mut symbols = Symbols::default();
+    for (index, line) in text.lines().enumerate() {
+        if line.is_empty() {
+            continue;
+        }
+        let line_parser = Line { line: index + 1 };
+        let fields = line.split('\t').map(unescape).collect::<Vec<_>>();
+        match fields.first().map(String::as_str) {
+            Some("choice") => symbols.choices.push(line_parser.choice(fields)?),
+            _ => symbols.symbols.push(line_parser.symbol(fields)?),
+        }
+    }
+    Ok(symbols)
+}
+
+/// Parsing of a single line, which knows the line number for errors.
+struct Line {
+    line: usize,
+}
+
+impl Line {
+    fn error<T>(&self, message: impl Into<String>) -> Result<T> {
+        Err(ParseError { line: self.line, message: message.into() })
+    }
+
+    fn symbol(&self, fields: Vec<String>) -> Result<Symbol> {
+        let mut fields = fields.into_iter();
+        let kind = fields.next().unwrap_or_default();
+        let name = self.name(fields.next())?;
+        let value = fields.next().unwrap_or_default();
+
+        let value = match kind.as_str() {
+            "bool" => match value.as_str() {
+                "y" => Value::Bool(true),
+                "n" => Value::Bool(false),
+                _ => return self.error(format!("invalid bool value {:?}", value)),
+            },
+            "int" => Value::Int {
+                value: self.int(&value)?,
+                range: self.range(fields.next(), fields.next(), |text| self.int(text))?,
+            },
+            "hex" => Value::Hex {
+                value: self.hex(&value)?,
+                range: self.range(fields.next(), fields.next(), |text| self.hex(text))?,
+            },
+            "string" => Value::Str(value),
+            _ => return self.error(format!("unknown symbol type {:?}", kind)),
+        };
+
+        let prompt = fields.next().unwrap_or_default();
+        Ok(Symbol { name, value, prompt })
+    }
+
+    fn choice(&self, fields: Vec<String>) -> Result<Choice> {
+        let mut fields = fields.into_iter().skip(1);
+        let name = self.name(fields.next())?;
+        let selection = fields.next().filter(|sel| !sel.is_empty());
+        let symbols = fields
+            .next()
+            .unwrap_or_default()
+            .split_whitespace()
+            .map(String::from)
+            .collect();
+        let prompt = fields.next().unwrap_or_default();
+        Ok(Choice { name, symbols, selection, prompt })
+    }
+
+    fn name(&self, field: Option<String>) -> Result<String> {
+        match field {
+            Some(name) if !name.is_empty() => Ok(name),
+            _ => self.error("missing name"),
+        }
+    }
+
+    fn int(&self, text: &str) -> Result<i64> {
+        text.parse().or_else(|_| self.error(format!("invalid int value {:?}", text)))
+    }
+
+    fn hex(&self, text: &str) -> Result<u64> {
+        let digits = text.strip_prefix("0x").unwrap_or(text);
+        u64::from_str_radix(digits, 16)
+            .or_else(|_| self.error(format!("invalid hex value {:?}", text)))
+    }
+
+    /// The range, from the low and high fields, which are both empty if there is no range.
+    fn range<T>(
+        &self,
+        low: Option<String>,
+        high: Option<String>,
+        value: impl Fn(&str) -> Result<T>,
+    ) -> Result<Option<(T, T)>> {
+        match (low.unwrap_or_default(), high.unwrap_or_default()) {
+            (low, high) if low.is_empty() && high.is_empty() => Ok(None),
+            (low, high) => Ok(Some((value(&low)?, value(&high)?))),
+        }
+    }
+}
+
+/// Undo the escaping of backslashes, tabs and newlines.
+fn unescape(field: &str) -> String {
+    let mut result = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => result.push('\t'),
+            Some('n') => result.push('\n'),
+            Some(other) => result.push(other),
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::error_line;
+
+    fn value(line: &str) -> Value {
+        parse(line).unwrap().symbols.swap_remove(0).value
+    }
+
+    #[test]
+    fn bools() {
+        let symbols = parse("bool\tCONFIG_YES\ty\tA prompt\n\nbool\tCONFIG_NO\tn\t\n").unwrap();
+        assert_eq!(symbols.symbols.len(), 2);
+        let yes = &symbols.symbols[0];
+        assert_eq!(yes.name, "CONFIG_YES");
+        assert!(matches!(yes.value, Value::Bool(true)));
+        assert_eq!(yes.prompt, "A prompt");
+        assert!(matches!(symbols.symbols[1].value, Value::Bool(false)));
+        assert_eq!(symbols.symbols[1].prompt, "");
+    }
+
+    #[test]
+    fn ints() {
+        assert!(matches!(value("int\tCONFIG_A\t5\t\t\t"), Value::Int { value: 5, range: None }));
+        assert!(matches!(
+            value("int\tCONFIG_A\t-3\t-10\t10\t"),
+            Value::Int { value: -3, range: Some((-10, 10)) }
+        ));
+        assert!(matches!(
+            value("int\tCONFIG_A\t5000000000\t0\t10000000000\t"),
+            Value::Int { value: 5_000_000_000, range: Some((0, 10_000_000_000)) }
+        ));
+    }
+
+    #[test]
+    fn hexes() {
+        assert!(matches!(
+            value("hex\tCONFIG_A\t0x1f\t\t\t"),
+            Value::Hex { value: 0x1f, range: None }
+        ));
+        assert!(matches!(
+            value("hex\tCONFIG_A\t0x10\t0x0\t0x100000000\t"),
+            Value::Hex { value: 0x10, range: Some((0, 0x1_0000_0000)) }
+        ));
+        assert!(matches!(
+            value("hex\tCONFIG_A\tffffffffffffffff\t\t\t"),
+            Value::Hex { value: u64::MAX, range: None }
+        ));
+    }
+
+    #[test]
+    fn strings() {
+        match value("string\tCONFIG_A\tsay \"hi\"\\\\\\tthere\\n\t") {
+            Value::Str(text) => assert_eq!(text, "say \"hi\"\\\tthere\n"),
+            other => panic!("unexpected value {:?}", other),
+        }
+        assert_eq!(unescape("trailing\\"), "trailing\\");
+    }
+
+    #[test]
+    fn choices() {
+        let symbols = parse(
+            "choice\tCONFIG_MODE\tCONFIG_FAST\tCONFIG_SLOW CONFIG_FAST\tMode\n\
+             choice\tCONFIG_UNSET\t\tCONFIG_A\t\n",
+        )
+        .unwrap();
+        let mode = &symbols.choices[0];
+        assert_eq!(mode.name, "CONFIG_MODE");
+        assert_eq!(mode.selection.as_deref(), Some("CONFIG_FAST"));
+        assert_eq!(mode.symbols, ["CONFIG_SLOW", "CONFIG_FAST"]);
+        assert_eq!(mode.prompt, "Mode");
+        assert_eq!(symbols.choices[1].selection, None);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(error_line(parse("bool\tCONFIG_A\ty\t\nbool\tCONFIG_B\tm\t\n")), 2);
+        assert_eq!(error_line(parse("int\tCONFIG_A\t0x10\t\t\t\n")), 1);
+        assert_eq!(error_line(parse("int\tCONFIG_A\t1\t0\t\t\n")), 1);
+        assert_eq!(error_line(parse("hex\tCONFIG_A\t0xg\t\t\t\n")), 1);
+        assert_eq!(error_line(parse("\nbool\t\ty\t\n")), 2);
+        assert_eq!(error_line(parse("tristate\tCONFIG_A\ty\t\n")), 1);
+        let error = parse("bool\tCONFIG_A\tm\t\n").unwrap_err();
+        assert_eq!(error.to_string(), "line 1: invalid bool value \"m\"");
+    }
+}
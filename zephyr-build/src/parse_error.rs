@@ -0,0 +1,23 @@
+// Copyright (c) 2024 Linaro LTD
+// SPDX-License-Identifier: Apache-2.0
+
+//! The error type shared by the Kconfig symbol and devicetree parsers.
+
+use std::fmt;
+
+/// An error encountered while parsing, at the given line of the input.
+#[derive(Debug)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+pub(crate) type Result<T> = std::result::Result<T, ParseError>;
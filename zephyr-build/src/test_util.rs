@@ -0,0 +1,21 @@
+// Copyright (c) 2024 Linaro LTD
+// SPDX-License-Identifier: Apache-2.0
+
+//! Helpers shared by the unit tests.
+
+use std::fmt::Debug;
+use std::io;
+
+use crate::parse_error::Result;
+
+/// The line a parse failed at.  Panics if the parse succeeded.
+pub fn error_line<T: Debug>(result: Result<T>) -> usize {
+    result.unwrap_err().line
+}
+
+/// Run a generator over a buffer, returning the generated text along with the generator's result.
+pub fn generated<T>(generate: impl FnOnce(&mut Vec<u8>) -> io::Result<T>) -> (String, T) {
+    let mut out = Vec::new();
+    let result = generate(&mut out).unwrap();
+    (String::from_utf8(out).unwrap(), result)
+}
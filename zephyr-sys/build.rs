@@ -29,23 +29,7 @@ fn main() -> Result<()> {
     // And get the root of the zephyr tree.
     let zephyr_base = env::var("ZEPHYR_BASE")?;
 
-    // Rustc uses some complex target tuples for the riscv targets, whereas clang uses other
-    // options.  Fortunately, these variants shouldn't affect the structures generated, so just
-    // turn this into a generic target.
-    let target = if target.starts_with("riscv32") {
-        "riscv32-unknown-none-elf".to_string()
-    } else {
-        target
-    };
-
-    // Likewise, do the same with RISCV-64.
-    let target = if target.starts_with("riscv64") {
-        "riscv64-unknown-none-elf".to_string()
-    } else {
-        target
-    };
-
-    let target_arg = format!("--target={}", target);
+    let target_arg = format!("--target={}", clang_target(&target));
 
     // println!("includes: {:?}", env::var("INCLUDE_DIRS"));
     // println!("defines: {:?}", env::var("INCLUDE_DEFINES"));
@@ -70,7 +54,17 @@ fn main() -> Result<()> {
         .derive_copy(false)
         .allowlist_function("k_.*")
         .allowlist_function("gpio_.*")
+        .allowlist_function("i2c_.*")
+        .allowlist_function("spi_.*")
+        .allowlist_function("uart_.*")
+        .allowlist_function("device_.*")
         .allowlist_function("sys_.*")
+        .allowlist_var("E[A-Z0-9]+")
+        .allowlist_var("GPIO_.*")
+        .allowlist_var("I2C_.*")
+        .allowlist_var("SPI_.*")
+        .allowlist_var("UART_.*")
+        .allowlist_var("ZR_.*")
         // Deprecated
         .blocklist_function("sys_clock_timeout_end_calc")
         .parse_callbacks(Box::new(bindgen::CargoCallbacks::new()))
@@ -84,6 +78,38 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+/// The target to give clang for a given Rust target.
+fn clang_target(target: &str) -> String {
+    // Rustc uses some complex target tuples for the riscv targets, whereas clang uses other
+    // options.  Fortunately, these variants shouldn't affect the structures generated, so just
+    // turn this into a generic target.
+    if target.starts_with("riscv32") {
+        return "riscv32-unknown-none-elf".to_string();
+    }
+
+    // Likewise, do the same with RISCV-64.
+    if target.starts_with("riscv64") {
+        return "riscv64-unknown-none-elf".to_string();
+    }
+
+    // The soft-float variant only changes the code rustc generates, not the data layout, and isn't
+    // a target clang knows.
+    if let Some(base) = target.strip_suffix("-softfloat") {
+        return base.to_string();
+    }
+
+    // The Linux targets are used for native_sim, which really is a Linux program, but also for
+    // 32-bit x86, which has no bare metal target.  For the latter, keep clang from defining
+    // `__linux__`, and from searching the host headers.  The Kconfig cfgs are given to every crate
+    // as rustflags, and so appear as `CARGO_CFG_` variables here.
+    let posix = env::var_os("CARGO_CFG_CONFIG_ARCH_POSIX").is_some();
+    if target.ends_with("-unknown-linux-gnu") && !posix {
+        return target.replace("-linux-gnu", "-none-elf");
+    }
+
+    target.to_string()
+}
+
 fn define_args(bindings: Builder, prefix: &str, var_name: &str) -> Builder {
     let text = env::var(var_name).unwrap();
     let mut bindings = bindings;
@@ -30,5 +30,61 @@ extern int errno;
 #endif
 #endif
 
+#include <errno.h>
 #include <zephyr/kernel.h>
 #include <zephyr/drivers/gpio.h>
+#include <zephyr/drivers/i2c.h>
+#include <zephyr/drivers/spi.h>
+#include <zephyr/drivers/uart.h>
+
+/*
+ * bindgen will only output #defined constants that resolve to simple numbers.  Some values that
+ * are needed from Rust are more complex than this, generally involving other macros or casts.
+ * Capture these as constants, with a "ZR_" prefix to avoid conflicts with other symbols.
+ */
+
+/* The base alignment of thread stacks, as used by K_THREAD_STACK_DEFINE. */
+const uintptr_t ZR_STACK_ALIGN = Z_THREAD_STACK_OBJ_ALIGN(1);
+
+/* The portion of a thread stack reserved by the kernel. */
+const uintptr_t ZR_STACK_RESERVED = K_THREAD_STACK_RESERVED;
+
+/* K_THREAD_STACK_LEN for a few sizes, to check the Rust computation of it against. */
+const uintptr_t ZR_STACK_LEN_100 = K_THREAD_STACK_LEN(100);
+const uintptr_t ZR_STACK_LEN_1000 = K_THREAD_STACK_LEN(1000);
+const uintptr_t ZR_STACK_LEN_4096 = K_THREAD_STACK_LEN(4096);
+
+#ifdef CONFIG_POLL
+/* The poll event type and mode used to wait on a `k_poll_signal`. */
+const uint32_t ZR_POLL_TYPE_SIGNAL = K_POLL_TYPE_SIGNAL;
+const int ZR_POLL_MODE_NOTIFY_ONLY = K_POLL_MODE_NOTIFY_ONLY;
+#endif
+
+#ifdef CONFIG_LOG
+#include <zephyr/logging/log.h>
+
+/* The sizes of the log source structures, as Rust defines its own in the same tables. */
+const uintptr_t ZR_LOG_CONST_SIZE = sizeof(struct log_source_const_data);
+#ifdef CONFIG_LOG_RUNTIME_FILTERING
+const uintptr_t ZR_LOG_DYNAMIC_SIZE = sizeof(struct log_source_dynamic_data);
+#endif
+#endif
+
+/* I2C message flags. */
+const uint8_t ZR_I2C_MSG_WRITE = I2C_MSG_WRITE;
+const uint8_t ZR_I2C_MSG_READ = I2C_MSG_READ;
+const uint8_t ZR_I2C_MSG_STOP = I2C_MSG_STOP;
+const uint8_t ZR_I2C_MSG_RESTART = I2C_MSG_RESTART;
+const uint8_t ZR_I2C_MSG_ADDR_10_BITS = I2C_MSG_ADDR_10_BITS;
+
+/* SPI operation flags. */
+const spi_operation_t ZR_SPI_OP_MODE_MASTER = SPI_OP_MODE_MASTER;
+const spi_operation_t ZR_SPI_OP_MODE_SLAVE = SPI_OP_MODE_SLAVE;
+const spi_operation_t ZR_SPI_MODE_CPOL = SPI_MODE_CPOL;
+const spi_operation_t ZR_SPI_MODE_CPHA = SPI_MODE_CPHA;
+const spi_operation_t ZR_SPI_MODE_LOOP = SPI_MODE_LOOP;
+const spi_operation_t ZR_SPI_TRANSFER_MSB = SPI_TRANSFER_MSB;
+const spi_operation_t ZR_SPI_TRANSFER_LSB = SPI_TRANSFER_LSB;
+const spi_operation_t ZR_SPI_HOLD_ON_CS = SPI_HOLD_ON_CS;
+const spi_operation_t ZR_SPI_LOCK_ON = SPI_LOCK_ON;
+const spi_operation_t ZR_SPI_CS_ACTIVE_HIGH = SPI_CS_ACTIVE_HIGH;
@@ -3,15 +3,15 @@
 
 // Pre-build code for zephyr module.
 
-// This module makes the values from the generated .config available as conditional compilation.
-// Note that this only applies to the zephyr module, and the user's application will not be able to
-// see these definitions.  To make that work, this will need to be moved into a support crate which
-// can be invoked by the user's build.rs.
+// The bool values from the generated .config are given to every crate as cfgs by the cmake build.
+// This generates the `kconfig` module with the other values, and the devicetree module.
 
 // This builds a program that is run on the compilation host before the code is compiled.  It can
 // output configuration settings that affect the compilation.
 
 fn main() {
-    zephyr_build::export_bool_kconfig();
-    zephyr_build::build_kconfig_mod();
+    if let Err(e) = zephyr_build::build_kconfig_mod() {
+        panic!("Unable to generate the kconfig module: {}", e);
+    }
+    zephyr_build::build_dts();
 }
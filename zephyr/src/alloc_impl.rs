@@ -0,0 +1,35 @@
+// Copyright (c) 2024 Linaro LTD
+// SPDX-License-Identifier: Apache-2.0
+
+//! A global allocator on top of the Zephyr system heap.
+//!
+//! This is enabled with `CONFIG_RUST_ALLOC`.  The heap is shared with C code that uses `k_malloc`,
+//! and its size is increased by `CONFIG_HEAP_MEM_POOL_ADD_SIZE_RUST` to make room for Rust.
+//!
+//! Allocation failure is reported back to the `alloc` crate by returning null.  In a `no_std`
+//! build, its default handling of this is to panic, which goes through the panic handler in this
+//! crate.
+
+use core::alloc::{GlobalAlloc, Layout};
+use core::ffi::c_void;
+use core::mem;
+
+use crate::raw;
+
+/// The allocator.  Allocations are made with `k_aligned_alloc`, and freed with `k_free`.
+pub struct ZephyrAllocator;
+
+unsafe impl GlobalAlloc for ZephyrAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        // `k_aligned_alloc` requires the alignment to be a multiple of the pointer size.
+        let align = layout.align().max(mem::size_of::<*const c_void>());
+        raw::k_aligned_alloc(align, layout.size()) as *mut u8
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, _layout: Layout) {
+        raw::k_free(ptr as *mut c_void);
+    }
+}
+
+#[global_allocator]
+static ZEPHYR_ALLOCATOR: ZephyrAllocator = ZephyrAllocator;
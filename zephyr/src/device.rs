@@ -0,0 +1,110 @@
+// Copyright (c) 2024 Linaro LTD
+// SPDX-License-Identifier: Apache-2.0
+
+//! Device handles.
+//!
+//! A [`Device`] is a reference to a `struct device`, as used by every Zephyr driver API.  It is
+//! created from a devicetree node, which can be found through its path, a node label, an alias or
+//! a chosen node, and is only handed out once the device has been initialized successfully.  The
+//! driver wrappers in [`drivers`](crate::drivers) are built on it.
+//!
+//! ```ignore
+//! use zephyr::device::Device;
+//! use zephyr::devicetree::{aliases, chosen, labels};
+//!
+//! let i2c = Device::from_node(&labels::i2c0::NODE)?;
+//! let led_port = Device::from_node(aliases::led0::GPIOS[0].node)?;
+//! let console = Device::from_node(&chosen::zephyr_console::NODE)?;
+//! printkln!("console is {}", console.name());
+//! ```
+
+use core::ffi::CStr;
+use core::fmt;
+use core::ptr;
+
+use crate::devicetree::Node;
+use crate::error::Error;
+use crate::raw;
+
+/// The reasons a [`Device`] can't be obtained.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeviceError {
+    /// There is no device for the node, generally because it is disabled, or has no driver.
+    NotFound,
+    /// The device exists, but its initialization failed.
+    NotReady,
+}
+
+impl fmt::Display for DeviceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeviceError::NotFound => write!(f, "device not found"),
+            DeviceError::NotReady => write!(f, "device not ready"),
+        }
+    }
+}
+
+/// Both cases are `ENODEV`, which is what Zephyr uses for a device that isn't usable.
+impl From<DeviceError> for Error {
+    fn from(_value: DeviceError) -> Error {
+        Error(raw::ENODEV)
+    }
+}
+
+/// A handle to a ready device.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Device {
+    raw: *const raw::device,
+}
+
+// Devices are static, and the driver APIs can be called from any thread.
+unsafe impl Send for Device {}
+unsafe impl Sync for Device {}
+
+impl Device {
+    /// Get the device for the given devicetree node, checking that it is ready.
+    pub fn from_node(node: &Node) -> Result<Device, DeviceError> {
+        let raw = node.device.map_or(ptr::null(), |device| device());
+        if raw.is_null() {
+            return Err(DeviceError::NotFound);
+        }
+        // Safety: the pointer is the device defined for the node.
+        unsafe { Device::from_raw(raw) }
+    }
+
+    /// Get a device from a raw pointer, checking that it is ready.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must refer to a device defined by the Zephyr build.
+    pub unsafe fn from_raw(raw: *const raw::device) -> Result<Device, DeviceError> {
+        if raw::device_is_ready(raw) {
+            Ok(Device { raw })
+        } else {
+            Err(DeviceError::NotReady)
+        }
+    }
+
+    /// Check that the device is still ready.  The check is made when the handle is created, so
+    /// this is only needed for devices that can be suspended, or deinitialized.
+    pub fn is_ready(&self) -> bool {
+        unsafe { raw::device_is_ready(self.raw) }
+    }
+
+    /// The name of the device, as registered with the kernel.
+    pub fn name(&self) -> &'static str {
+        let name = unsafe { CStr::from_ptr((*self.raw).name) };
+        name.to_str().unwrap_or("?")
+    }
+
+    /// The underlying device, for use with the C API.
+    pub fn as_raw(&self) -> *const raw::device {
+        self.raw
+    }
+}
+
+impl fmt::Debug for Device {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Device {{ name: {:?} }}", self.name())
+    }
+}
@@ -0,0 +1,104 @@
+// Copyright (c) 2024 Linaro LTD
+// SPDX-License-Identifier: Apache-2.0
+
+//! Devicetree information for the current build.
+//!
+//! The contents of this module are generated by `zephyr-build` from the final `zephyr.dts`.  Each
+//! node in the tree becomes a module, nested in the same way as the tree, with the root node's
+//! properties directly in this module.  For example, `/soc/gpio@50000000` would be
+//! `zephyr::devicetree::soc::gpio_50000000`.
+//!
+//! Every node module contains a [`NODE`](Node) constant and a `LABELS` constant, along with a
+//! constant for each property that can be represented in Rust.  The type of the constant depends
+//! on the property:
+//!
+//! - `compatible` is always a `&[&str]`.
+//! - `reg` is a `&[Reg]`, split according to the parent's `#address-cells` and `#size-cells`.
+//! - `interrupts` is a `&[u32]` of the raw cells.
+//! - Boolean properties are `bool`, and are only present when true.
+//! - Strings are `&str`, or `&[&str]` for string lists.
+//! - A single cell is a `u32`, and multiple cells are a `&[u32]`.
+//! - A single phandle is a `&Node`, and a phandle array (such as `gpios`) is a `&[PhandleEntry]`.
+//!
+//! Property names are converted to upper case, with punctuation replaced by underscores, so
+//! `current-speed` becomes `CURRENT_SPEED`.
+//!
+//! In addition, the `labels` module has an entry for each node label, and the `chosen` and
+//! `aliases` modules refer to the nodes named in the `/chosen` and `/aliases` nodes.  These are
+//! re-exports of the node modules, so `zephyr::devicetree::aliases::led0::GPIOS` is the `gpios`
+//! property of the node the `led0` alias refers to.
+
+use crate::raw;
+
+/// Identification of a single node in the devicetree.
+#[derive(Debug)]
+pub struct Node {
+    /// The full path of the node.
+    pub path: &'static str,
+    /// The name of the node, including the unit address.
+    pub name: &'static str,
+    /// Returns the device defined for this node, which is the same one `DEVICE_DT_GET()` gives,
+    /// or null if there is no driver for it.  This is `None` if the node's ordinal isn't known.
+    /// Use [`Device::from_node`](crate::device::Device::from_node) rather than calling this.
+    pub device: Option<fn() -> *const raw::device>,
+}
+
+/// A single entry from a `reg` property.
+#[derive(Debug)]
+pub struct Reg {
+    pub addr: u64,
+    pub size: u64,
+}
+
+/// A single entry in a phandle array property, such as `gpios`.  This is the node referenced,
+/// along with the specifier cells that follow the reference.
+#[derive(Debug)]
+pub struct PhandleEntry {
+    pub node: &'static Node,
+    pub cells: &'static [u32],
+}
+
+#[cfg(target_pointer_width = "32")]
+macro_rules! asm_ptr {
+    () => {
+        ".4byte"
+    };
+}
+
+#[cfg(target_pointer_width = "64")]
+macro_rules! asm_ptr {
+    () => {
+        ".8byte"
+    };
+}
+
+/// Define a function `$name` returning `__device_dts_ord_$ord`, the device Zephyr defines for the
+/// node with that ordinal.  The symbol only exists if a driver defined the device, so it can't be
+/// referenced from Rust directly.  Instead, a pointer to it is placed in a constant with a weak
+/// reference, which the linker resolves to null when there is no device.
+macro_rules! device_ref {
+    ($name:ident, $ord:literal) => {
+        core::arch::global_asm!(
+            concat!(".weak __device_dts_ord_", $ord),
+            concat!(".pushsection .rodata.zr_device_dts_ord_", $ord, ",\"a\""),
+            concat!(".globl zr_device_dts_ord_", $ord),
+            concat!(".hidden zr_device_dts_ord_", $ord),
+            ".balign {align}",
+            concat!("zr_device_dts_ord_", $ord, ":"),
+            concat!(asm_ptr!(), " __device_dts_ord_", $ord),
+            ".popsection",
+            align = const core::mem::align_of::<usize>(),
+        );
+
+        fn $name() -> *const $crate::raw::device {
+            extern "C" {
+                #[link_name = concat!("zr_device_dts_ord_", $ord)]
+                static DEVICE: *const $crate::raw::device;
+            }
+            unsafe { DEVICE }
+        }
+    };
+}
+
+// Bring in the generated tree.
+include!(concat!(env!("OUT_DIR"), "/devicetree.rs"));
@@ -0,0 +1,18 @@
+// Copyright (c) 2024 Linaro LTD
+// SPDX-License-Identifier: Apache-2.0
+
+//! Safe wrappers around Zephyr drivers.
+//!
+//! Each driver class is only present if it is enabled in the Zephyr configuration.
+
+#[cfg(CONFIG_GPIO)]
+pub mod gpio;
+
+#[cfg(CONFIG_I2C)]
+pub mod i2c;
+
+#[cfg(CONFIG_SPI)]
+pub mod spi;
+
+#[cfg(CONFIG_SERIAL)]
+pub mod uart;
@@ -0,0 +1,206 @@
+// Copyright (c) 2024 Linaro LTD
+// SPDX-License-Identifier: Apache-2.0
+
+//! GPIO support.
+//!
+//! A [`GpioPin`] represents a single pin on a GPIO controller, as described by an entry in a
+//! devicetree `gpios` property.  This mirrors the `gpio_dt_spec` based API in C, with the
+//! devicetree flags (such as active low) applied to every operation, so `set(true)` means "make
+//! the pin active", regardless of the pin's polarity.
+//!
+//! ```ignore
+//! use zephyr::drivers::gpio::{GpioPin, GPIO_OUTPUT_ACTIVE};
+//!
+//! let mut led = GpioPin::from_dt(&zephyr::devicetree::aliases::led0::GPIOS[0])?;
+//! led.configure(GPIO_OUTPUT_ACTIVE)?;
+//! led.toggle()?;
+//! ```
+
+use core::cell::UnsafeCell;
+use core::ffi::c_int;
+use core::mem;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::device::Device;
+use crate::devicetree::PhandleEntry;
+use crate::error::{to_result, to_result_void, Error, Result};
+use crate::raw;
+
+/// Flags used to configure a pin, or its interrupt.
+pub type Flags = raw::gpio_flags_t;
+
+/// The commonly used configuration flags.  These can be combined with `|`.
+pub use crate::raw::{
+    GPIO_ACTIVE_HIGH,
+    GPIO_ACTIVE_LOW,
+    GPIO_DISCONNECTED,
+    GPIO_INPUT,
+    GPIO_OUTPUT,
+    GPIO_OUTPUT_ACTIVE,
+    GPIO_OUTPUT_HIGH,
+    GPIO_OUTPUT_INACTIVE,
+    GPIO_OUTPUT_LOW,
+    GPIO_PULL_DOWN,
+    GPIO_PULL_UP,
+};
+
+/// Interrupt configuration flags, for [`GpioPin::interrupt_configure`].
+pub use crate::raw::{
+    GPIO_INT_DISABLE,
+    GPIO_INT_EDGE_BOTH,
+    GPIO_INT_EDGE_FALLING,
+    GPIO_INT_EDGE_RISING,
+    GPIO_INT_EDGE_TO_ACTIVE,
+    GPIO_INT_EDGE_TO_INACTIVE,
+    GPIO_INT_LEVEL_ACTIVE,
+    GPIO_INT_LEVEL_INACTIVE,
+};
+
+/// A single GPIO pin.
+pub struct GpioPin {
+    spec: raw::gpio_dt_spec,
+}
+
+// The device pointer refers to a static device, and the GPIO API can be called from any thread.
+unsafe impl Send for GpioPin {}
+
+impl GpioPin {
+    /// Construct a pin from an entry of a devicetree `gpios` property.  The first specifier cell
+    /// is the pin number, and the second, if present, holds the devicetree flags.  Fails with
+    /// `ENODEV` if the controller is not ready.
+    pub fn from_dt(entry: &PhandleEntry) -> Result<GpioPin> {
+        Ok(GpioPin { spec: dt_spec(entry)? })
+    }
+
+    /// The pin number within the controller.
+    pub fn pin(&self) -> u32 {
+        self.spec.pin as u32
+    }
+
+    /// The underlying pin specification, for use with the C API.
+    pub fn as_raw(&self) -> &raw::gpio_dt_spec {
+        &self.spec
+    }
+
+    /// Determine if the controller for this pin is ready.
+    pub fn is_ready(&self) -> bool {
+        unsafe { raw::gpio_is_ready_dt(&self.spec) }
+    }
+
+    /// Configure the pin.  The given flags are combined with the flags from the devicetree.
+    pub fn configure(&mut self, extra_flags: Flags) -> Result<()> {
+        to_result_void(unsafe { raw::gpio_pin_configure_dt(&self.spec, extra_flags) })
+    }
+
+    /// Set the logical level of an output pin.  `true` makes the pin active.
+    pub fn set(&mut self, value: bool) -> Result<()> {
+        to_result_void(unsafe { raw::gpio_pin_set_dt(&self.spec, value as c_int) })
+    }
+
+    /// Read the logical level of the pin.  `true` indicates the pin is active.
+    pub fn get(&self) -> Result<bool> {
+        to_result(unsafe { raw::gpio_pin_get_dt(&self.spec) }).map(|value| value != 0)
+    }
+
+    /// Toggle an output pin.
+    pub fn toggle(&mut self) -> Result<()> {
+        to_result_void(unsafe { raw::gpio_pin_toggle_dt(&self.spec) })
+    }
+
+    /// Configure the interrupt for this pin, using one of the `GPIO_INT_*` flags.
+    pub fn interrupt_configure(&mut self, flags: Flags) -> Result<()> {
+        to_result_void(unsafe { raw::gpio_pin_interrupt_configure_dt(&self.spec, flags) })
+    }
+
+    /// Register a callback to be invoked when this pin's interrupt fires.  The interrupt itself
+    /// must also be enabled with [`interrupt_configure`](Self::interrupt_configure).
+    ///
+    /// A callback can only be registered with a single pin at a time.  Fails with `EBUSY` if it is
+    /// already registered, as reinitializing it would corrupt the list it is on.
+    pub fn add_callback(&mut self, callback: &'static GpioCallback) -> Result<()> {
+        if callback
+            .registered
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .is_err()
+        {
+            return Err(Error(raw::EBUSY));
+        }
+        let result = unsafe {
+            raw::gpio_init_callback(callback.raw.get(), Some(gpio_trampoline), 1 << self.spec.pin);
+            to_result_void(raw::gpio_add_callback_dt(&self.spec, callback.raw.get()))
+        };
+        if result.is_err() {
+            callback.registered.store(false, Ordering::Release);
+        }
+        result
+    }
+
+    /// Remove a callback previously registered with [`add_callback`](Self::add_callback), after
+    /// which it can be registered again.
+    pub fn remove_callback(&mut self, callback: &'static GpioCallback) -> Result<()> {
+        to_result_void(unsafe { raw::gpio_remove_callback_dt(&self.spec, callback.raw.get()) })?;
+        callback.registered.store(false, Ordering::Release);
+        Ok(())
+    }
+}
+
+/// Build a `gpio_dt_spec` from an entry of a devicetree `gpios` property.
+pub(crate) fn dt_spec(entry: &PhandleEntry) -> Result<raw::gpio_dt_spec> {
+    let (pin, flags) = match entry.cells {
+        [pin] => (*pin, 0),
+        [pin, flags, ..] => (*pin, *flags),
+        [] => return Err(Error(raw::EINVAL)),
+    };
+
+    let port = Device::from_node(entry.node)?;
+
+    Ok(raw::gpio_dt_spec {
+        port: port.as_raw(),
+        pin: pin as raw::gpio_pin_t,
+        dt_flags: flags as raw::gpio_dt_flags_t,
+    })
+}
+
+/// A callback that can be registered for GPIO interrupts.
+///
+/// The callback must be static, as Zephyr keeps a reference to it once it is registered.  The
+/// handler is called from interrupt context, with the mask of the pins that triggered.
+///
+/// ```ignore
+/// static BUTTON_CB: GpioCallback = GpioCallback::new(&|pins| {
+///     printkln!("Pressed: {:#x}", pins);
+/// });
+/// ```
+#[repr(C)]
+pub struct GpioCallback {
+    // Must be first, as the trampoline recovers the GpioCallback from this pointer.
+    raw: UnsafeCell<raw::gpio_callback>,
+    handler: &'static (dyn Fn(u32) + Sync),
+    /// Set while the callback is on a controller's list.
+    registered: AtomicBool,
+}
+
+// The raw callback is only modified while registering, which `registered` makes exclusive, and
+// otherwise only used by the GPIO driver.
+unsafe impl Sync for GpioCallback {}
+
+impl GpioCallback {
+    /// Create a new callback, with the given handler.
+    pub const fn new(handler: &'static (dyn Fn(u32) + Sync)) -> GpioCallback {
+        GpioCallback {
+            // The list node and pin mask are initialized when the callback is added.
+            raw: UnsafeCell::new(unsafe { mem::zeroed() }),
+            handler,
+            registered: AtomicBool::new(false),
+        }
+    }
+}
+
+unsafe extern "C" fn gpio_trampoline(
+    _port: *const raw::device,
+    cb: *mut raw::gpio_callback,
+    pins: raw::gpio_port_pins_t,
+) {
+    let callback = &*(cb as *const GpioCallback);
+    (callback.handler)(pins);
+}
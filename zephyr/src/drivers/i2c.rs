@@ -0,0 +1,99 @@
+// Copyright (c) 2024 Linaro LTD
+// SPDX-License-Identifier: Apache-2.0
+
+//! I2C support.
+//!
+//! An [`I2c`] is a handle to an I2C controller.  The target address is given with each transfer,
+//! so a single handle can be used to talk to every device on the bus.
+//!
+//! ```ignore
+//! use zephyr::drivers::i2c::I2c;
+//!
+//! let mut i2c = I2c::from_dt(&zephyr::devicetree::labels::i2c0::NODE)?;
+//! let mut id = [0u8; 1];
+//! i2c.write_read(0x48, &[0x0f], &mut id)?;
+//! ```
+
+use core::ffi::c_void;
+
+use crate::device::Device;
+use crate::devicetree::Node;
+use crate::error::{to_result_void, Error, Result};
+use crate::raw;
+
+/// A single message of a transfer.
+pub type Msg = raw::i2c_msg;
+
+/// Flags for each [`Msg`] of a transfer.
+pub const I2C_MSG_WRITE: u8 = raw::ZR_I2C_MSG_WRITE;
+pub const I2C_MSG_READ: u8 = raw::ZR_I2C_MSG_READ;
+pub const I2C_MSG_STOP: u8 = raw::ZR_I2C_MSG_STOP;
+pub const I2C_MSG_RESTART: u8 = raw::ZR_I2C_MSG_RESTART;
+pub const I2C_MSG_ADDR_10_BITS: u8 = raw::ZR_I2C_MSG_ADDR_10_BITS;
+
+/// An I2C controller.
+pub struct I2c {
+    dev: Device,
+}
+
+impl I2c {
+    /// Construct a handle for the given I2C controller.
+    pub fn new(dev: Device) -> I2c {
+        I2c { dev }
+    }
+
+    /// Construct a handle for the I2C controller described by the given devicetree node.  Fails
+    /// with `ENODEV` if the controller is not ready.
+    pub fn from_dt(node: &Node) -> Result<I2c> {
+        Ok(I2c::new(Device::from_node(node)?))
+    }
+
+    /// The controller device.
+    pub fn device(&self) -> Device {
+        self.dev
+    }
+
+    /// The underlying device, for use with the C API.
+    pub fn as_raw(&self) -> *const raw::device {
+        self.dev.as_raw()
+    }
+
+    /// Determine if the controller is ready.
+    pub fn is_ready(&self) -> bool {
+        self.dev.is_ready()
+    }
+
+    /// Write `data` to the device at `addr`.
+    pub fn write(&mut self, addr: u16, data: &[u8]) -> Result<()> {
+        to_result_void(unsafe { raw::i2c_write(self.as_raw(), data.as_ptr(), data.len() as u32, addr) })
+    }
+
+    /// Read from the device at `addr`, filling `buf`.
+    pub fn read(&mut self, addr: u16, buf: &mut [u8]) -> Result<()> {
+        to_result_void(unsafe { raw::i2c_read(self.as_raw(), buf.as_mut_ptr(), buf.len() as u32, addr) })
+    }
+
+    /// Write `data` to the device at `addr`, and then, after a restart, read from it into `buf`.
+    pub fn write_read(&mut self, addr: u16, data: &[u8], buf: &mut [u8]) -> Result<()> {
+        to_result_void(unsafe {
+            raw::i2c_write_read(
+                self.as_raw(),
+                addr,
+                data.as_ptr() as *const c_void,
+                data.len(),
+                buf.as_mut_ptr() as *mut c_void,
+                buf.len(),
+            )
+        })
+    }
+
+    /// Perform a transfer made up of the given messages, with the device at `addr`.
+    ///
+    /// # Safety
+    ///
+    /// The buffer of each message must be valid for its length, and writable for read messages.
+    pub unsafe fn transfer(&mut self, addr: u16, msgs: &mut [Msg]) -> Result<()> {
+        let count: u8 = msgs.len().try_into().map_err(|_| Error(raw::EINVAL))?;
+        to_result_void(raw::i2c_transfer(self.as_raw(), msgs.as_mut_ptr(), count, addr))
+    }
+}
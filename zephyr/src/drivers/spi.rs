@@ -0,0 +1,205 @@
+// Copyright (c) 2024 Linaro LTD
+// SPDX-License-Identifier: Apache-2.0
+
+//! SPI support.
+//!
+//! An [`Spi`] is a single device on an SPI bus.  This mirrors the `spi_dt_spec` based API in C: the
+//! handle holds the bus controller along with the configuration for the device, including the
+//! chip select, which is asserted for the duration of each transfer.
+//!
+//! ```ignore
+//! use zephyr::drivers::spi::{word_size, Spi, SpiConfig, SPI_OP_MODE_MASTER};
+//!
+//! let config = SpiConfig::new(1_000_000, SPI_OP_MODE_MASTER | word_size(8))
+//!     .with_cs(&zephyr::devicetree::labels::spi1::CS_GPIOS[0], 0);
+//! let mut spi = Spi::from_dt(&zephyr::devicetree::labels::spi1::NODE, config)?;
+//! let mut id = [0u8; 2];
+//! spi.transfer(&mut id, &[0x9f])?;
+//! ```
+
+use core::ffi::c_void;
+use core::fmt;
+use core::mem;
+use core::ptr;
+
+use crate::device::Device;
+use crate::devicetree::{Node, PhandleEntry};
+use crate::error::{to_result_void, Result};
+use crate::raw;
+
+/// The operation flags of a device configuration.
+pub type Operation = raw::spi_operation_t;
+
+/// Operation flags, which can be combined with `|`.
+pub const SPI_OP_MODE_MASTER: Operation = raw::ZR_SPI_OP_MODE_MASTER;
+pub const SPI_OP_MODE_SLAVE: Operation = raw::ZR_SPI_OP_MODE_SLAVE;
+pub const SPI_MODE_CPOL: Operation = raw::ZR_SPI_MODE_CPOL;
+pub const SPI_MODE_CPHA: Operation = raw::ZR_SPI_MODE_CPHA;
+pub const SPI_MODE_LOOP: Operation = raw::ZR_SPI_MODE_LOOP;
+pub const SPI_TRANSFER_MSB: Operation = raw::ZR_SPI_TRANSFER_MSB;
+pub const SPI_TRANSFER_LSB: Operation = raw::ZR_SPI_TRANSFER_LSB;
+pub const SPI_CS_ACTIVE_HIGH: Operation = raw::ZR_SPI_CS_ACTIVE_HIGH;
+
+/// The operation flag for the given word size in bits, the equivalent of `SPI_WORD_SET`.
+pub const fn word_size(bits: Operation) -> Operation {
+    bits << raw::SPI_WORD_SIZE_SHIFT
+}
+
+/// The configuration of a device on the bus.
+pub struct SpiConfig {
+    frequency: u32,
+    operation: Operation,
+    slave: u16,
+    cs: Option<(&'static PhandleEntry, u32)>,
+}
+
+impl SpiConfig {
+    /// A configuration with the given bus frequency in Hz, and operation flags.  The device is
+    /// slave number zero, with no GPIO chip select.
+    pub const fn new(frequency: u32, operation: Operation) -> SpiConfig {
+        SpiConfig {
+            frequency,
+            operation,
+            slave: 0,
+            cs: None,
+        }
+    }
+
+    /// Set the slave number, for controllers that manage their own chip selects.
+    pub const fn with_slave(self, slave: u16) -> SpiConfig {
+        SpiConfig { slave, ..self }
+    }
+
+    /// Use a GPIO chip select, from an entry of the controller's `cs-gpios` property.  `delay` is
+    /// the time in microseconds to wait after asserting, and before releasing, the chip select.
+    pub const fn with_cs(self, entry: &'static PhandleEntry, delay: u32) -> SpiConfig {
+        SpiConfig { cs: Some((entry, delay)), ..self }
+    }
+}
+
+impl fmt::Debug for SpiConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SpiConfig {{ .. }}")
+    }
+}
+
+/// A device on an SPI bus.
+pub struct Spi {
+    spec: raw::spi_dt_spec,
+}
+
+// The bus pointer refers to a static device, and the SPI API can be called from any thread.
+unsafe impl Send for Spi {}
+
+impl Spi {
+    /// Construct a device on the given SPI controller.  This fails if the chip select GPIO can't be
+    /// used.
+    pub fn new(bus: Device, config: SpiConfig) -> Result<Spi> {
+        let mut spec: raw::spi_dt_spec = unsafe { mem::zeroed() };
+        spec.bus = bus.as_raw();
+        spec.config.frequency = config.frequency;
+        spec.config.operation = config.operation;
+        spec.config.slave = config.slave;
+        if let Some((entry, delay)) = config.cs {
+            spec.config.cs.gpio = cs_spec(entry)?;
+            spec.config.cs.delay = delay;
+        }
+
+        Ok(Spi { spec })
+    }
+
+    /// Construct a device on the SPI controller described by the given devicetree node.  Fails
+    /// with `ENODEV` if the controller is not ready.
+    pub fn from_dt(bus: &Node, config: SpiConfig) -> Result<Spi> {
+        Spi::new(Device::from_node(bus)?, config)
+    }
+
+    /// The underlying bus and configuration, for use with the C API.
+    pub fn as_raw(&self) -> &raw::spi_dt_spec {
+        &self.spec
+    }
+
+    /// Determine if the controller, and the chip select GPIO if any, are ready.
+    pub fn is_ready(&self) -> bool {
+        unsafe { raw::spi_is_ready_dt(&self.spec) }
+    }
+
+    /// Write `data` to the device, discarding anything read.
+    pub fn write(&mut self, data: &[u8]) -> Result<()> {
+        self.transceive(Some(data), None)
+    }
+
+    /// Read from the device into `buf`.
+    pub fn read(&mut self, buf: &mut [u8]) -> Result<()> {
+        self.transceive(None, Some(buf))
+    }
+
+    /// Write `data` while reading into `buf`.  If the lengths differ, the shorter side is padded:
+    /// extra reads are discarded, and extra writes are of an implementation defined value.
+    pub fn transfer(&mut self, buf: &mut [u8], data: &[u8]) -> Result<()> {
+        self.transceive(Some(data), Some(buf))
+    }
+
+    /// Write the contents of `buf`, replacing them with the data read.
+    pub fn transfer_in_place(&mut self, buf: &mut [u8]) -> Result<()> {
+        let tx = raw::spi_buf {
+            buf: buf.as_mut_ptr() as *mut c_void,
+            len: buf.len(),
+        };
+        let rx = raw::spi_buf {
+            buf: buf.as_mut_ptr() as *mut c_void,
+            len: buf.len(),
+        };
+        let tx_set = raw::spi_buf_set { buffers: &tx, count: 1 };
+        let rx_set = raw::spi_buf_set { buffers: &rx, count: 1 };
+        to_result_void(unsafe { raw::spi_transceive_dt(&self.spec, &tx_set, &rx_set) })
+    }
+
+    /// Release the chip select and the bus, after transfers made while they were held.
+    pub fn release(&mut self) -> Result<()> {
+        to_result_void(unsafe { raw::spi_release_dt(&self.spec) })
+    }
+
+    /// Set whether the chip select, and the lock on the bus, are held between transfers, allowing
+    /// several transfers to form one transaction.  Once held, they must be released with
+    /// [`release`](Self::release).
+    pub fn set_hold(&mut self, hold: bool) {
+        let flags = raw::ZR_SPI_HOLD_ON_CS | raw::ZR_SPI_LOCK_ON;
+        if hold {
+            self.spec.config.operation |= flags;
+        } else {
+            self.spec.config.operation &= !flags;
+        }
+    }
+
+    fn transceive(&mut self, tx: Option<&[u8]>, rx: Option<&mut [u8]>) -> Result<()> {
+        let tx_buf = tx.map(|data| raw::spi_buf {
+            buf: data.as_ptr() as *mut c_void,
+            len: data.len(),
+        });
+        let rx_buf = rx.map(|buf| raw::spi_buf {
+            buf: buf.as_mut_ptr() as *mut c_void,
+            len: buf.len(),
+        });
+        let tx_set = tx_buf.as_ref().map(|buf| raw::spi_buf_set { buffers: buf, count: 1 });
+        let rx_set = rx_buf.as_ref().map(|buf| raw::spi_buf_set { buffers: buf, count: 1 });
+        to_result_void(unsafe {
+            raw::spi_transceive_dt(
+                &self.spec,
+                tx_set.as_ref().map_or(ptr::null(), |set| set),
+                rx_set.as_ref().map_or(ptr::null(), |set| set),
+            )
+        })
+    }
+}
+
+#[cfg(CONFIG_GPIO)]
+fn cs_spec(entry: &PhandleEntry) -> Result<raw::gpio_dt_spec> {
+    crate::drivers::gpio::dt_spec(entry)
+}
+
+// Without GPIO support, there can't be a GPIO chip select.
+#[cfg(not(CONFIG_GPIO))]
+fn cs_spec(_entry: &PhandleEntry) -> Result<raw::gpio_dt_spec> {
+    Err(crate::error::Error(raw::ENOTSUP))
+}
@@ -0,0 +1,268 @@
+// Copyright (c) 2024 Linaro LTD
+// SPDX-License-Identifier: Apache-2.0
+
+//! UART support.
+//!
+//! A [`Uart`] is a handle to a UART, using the polling API, where each character is sent or
+//! received by the calling thread.  This is simple, but receiving only works while a thread is
+//! waiting for data.
+//!
+//! For a UART that receives in the background, a [`BufferedUart`] collects incoming data into a
+//! ring buffer, from interrupts, and sends from interrupts as well.  It can use either the
+//! interrupt driven API, or the async API, according to what the UART driver supports.
+//!
+//! With the `io` feature, both implement the `embedded-io` `Read` and `Write` traits, and with
+//! `io-async`, a `BufferedUart` also implements the `embedded-io-async` versions.
+//!
+//! ```ignore
+//! use zephyr::drivers::uart::{LineConfig, Uart};
+//!
+//! let mut uart = Uart::from_dt(&zephyr::devicetree::labels::uart1::NODE)?;
+//! uart.configure(&LineConfig::new(115_200))?;
+//! uart.send(b"AT\r\n");
+//! ```
+
+use crate::device::Device;
+use crate::devicetree::Node;
+use crate::error::{to_result_void, Result};
+use crate::raw;
+#[cfg(feature = "io")]
+use crate::time::{sleep, Duration};
+
+#[cfg(any(CONFIG_UART_INTERRUPT_DRIVEN, CONFIG_UART_ASYNC_API))]
+mod buffered;
+
+#[cfg(any(CONFIG_UART_INTERRUPT_DRIVEN, CONFIG_UART_ASYNC_API))]
+pub use buffered::BufferedUart;
+
+/// Parity setting.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Parity {
+    None = 0,
+    Odd = 1,
+    Even = 2,
+    Mark = 3,
+    Space = 4,
+}
+
+/// Number of stop bits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum StopBits {
+    Half = 0,
+    One = 1,
+    OneAndHalf = 2,
+    Two = 3,
+}
+
+/// Number of data bits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum DataBits {
+    Five = 0,
+    Six = 1,
+    Seven = 2,
+    Eight = 3,
+    Nine = 4,
+}
+
+/// Flow control setting.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum FlowControl {
+    None = 0,
+    RtsCts = 1,
+    DtrDsr = 2,
+    Rs485 = 3,
+}
+
+/// The line settings of a UART, matching `struct uart_config`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LineConfig {
+    pub baudrate: u32,
+    pub parity: Parity,
+    pub stop_bits: StopBits,
+    pub data_bits: DataBits,
+    pub flow_control: FlowControl,
+}
+
+impl LineConfig {
+    /// The common "8N1" settings, with no flow control, at the given baud rate.
+    pub const fn new(baudrate: u32) -> LineConfig {
+        LineConfig {
+            baudrate,
+            parity: Parity::None,
+            stop_bits: StopBits::One,
+            data_bits: DataBits::Eight,
+            flow_control: FlowControl::None,
+        }
+    }
+
+    #[cfg(CONFIG_UART_USE_RUNTIME_CONFIGURE)]
+    fn to_raw(self) -> raw::uart_config {
+        raw::uart_config {
+            baudrate: self.baudrate,
+            parity: self.parity as u8,
+            stop_bits: self.stop_bits as u8,
+            data_bits: self.data_bits as u8,
+            flow_ctrl: self.flow_control as u8,
+        }
+    }
+
+    /// Convert from the C version.  Values this doesn't know about give `EINVAL`.
+    #[cfg(CONFIG_UART_USE_RUNTIME_CONFIGURE)]
+    fn from_raw(config: &raw::uart_config) -> Result<LineConfig> {
+        use crate::error::Error;
+
+        let invalid = Error(raw::EINVAL);
+        Ok(LineConfig {
+            baudrate: config.baudrate,
+            parity: match config.parity {
+                0 => Parity::None,
+                1 => Parity::Odd,
+                2 => Parity::Even,
+                3 => Parity::Mark,
+                4 => Parity::Space,
+                _ => return Err(invalid),
+            },
+            stop_bits: match config.stop_bits {
+                0 => StopBits::Half,
+                1 => StopBits::One,
+                2 => StopBits::OneAndHalf,
+                3 => StopBits::Two,
+                _ => return Err(invalid),
+            },
+            data_bits: match config.data_bits {
+                0 => DataBits::Five,
+                1 => DataBits::Six,
+                2 => DataBits::Seven,
+                3 => DataBits::Eight,
+                4 => DataBits::Nine,
+                _ => return Err(invalid),
+            },
+            flow_control: match config.flow_ctrl {
+                0 => FlowControl::None,
+                1 => FlowControl::RtsCts,
+                2 => FlowControl::DtrDsr,
+                3 => FlowControl::Rs485,
+                _ => return Err(invalid),
+            },
+        })
+    }
+}
+
+/// A UART, using the polling API.
+pub struct Uart {
+    dev: Device,
+}
+
+impl Uart {
+    /// Construct a handle for the given UART.
+    pub fn new(dev: Device) -> Uart {
+        Uart { dev }
+    }
+
+    /// Construct a handle for the UART described by the given devicetree node.  Fails with `ENODEV`
+    /// if the UART is not ready.
+    pub fn from_dt(node: &Node) -> Result<Uart> {
+        Ok(Uart::new(Device::from_node(node)?))
+    }
+
+    /// The UART device.
+    pub fn device(&self) -> Device {
+        self.dev
+    }
+
+    /// Change the line settings.  Needs `CONFIG_UART_USE_RUNTIME_CONFIGURE`.
+    #[cfg(CONFIG_UART_USE_RUNTIME_CONFIGURE)]
+    pub fn configure(&mut self, config: &LineConfig) -> Result<()> {
+        let config = config.to_raw();
+        to_result_void(unsafe { raw::uart_configure(self.dev.as_raw(), &config) })
+    }
+
+    /// Read the current line settings.  Needs `CONFIG_UART_USE_RUNTIME_CONFIGURE`.
+    #[cfg(CONFIG_UART_USE_RUNTIME_CONFIGURE)]
+    pub fn config(&self) -> Result<LineConfig> {
+        let mut config: raw::uart_config = unsafe { core::mem::zeroed() };
+        to_result_void(unsafe { raw::uart_config_get(self.dev.as_raw(), &mut config) })?;
+        LineConfig::from_raw(&config)
+    }
+
+    /// Read a single character, if one is available.  This does not wait.
+    pub fn poll_in(&mut self) -> Option<u8> {
+        let mut c = 0u8;
+        match unsafe { raw::uart_poll_in(self.dev.as_raw(), &mut c) } {
+            0 => Some(c),
+            _ => None,
+        }
+    }
+
+    /// Send a single character, waiting until the UART can take it.
+    pub fn poll_out(&mut self, c: u8) {
+        unsafe { raw::uart_poll_out(self.dev.as_raw(), c) }
+    }
+
+    /// Send all of `data`, a character at a time.
+    pub fn send(&mut self, data: &[u8]) {
+        for &c in data {
+            self.poll_out(c);
+        }
+    }
+
+    /// Check for, and clear, receive errors.  Returns a bitmask of the `UART_ERROR_*` values, or
+    /// zero if there were no errors.
+    pub fn err_check(&mut self) -> Result<u32> {
+        crate::error::to_result(unsafe { raw::uart_err_check(self.dev.as_raw()) })
+            .map(|errors| errors as u32)
+    }
+}
+
+#[cfg(feature = "io")]
+impl embedded_io::Error for crate::error::Error {
+    fn kind(&self) -> embedded_io::ErrorKind {
+        embedded_io::ErrorKind::Other
+    }
+}
+
+#[cfg(feature = "io")]
+impl embedded_io::ErrorType for Uart {
+    type Error = crate::error::Error;
+}
+
+/// Reads wait for the first character by polling once per tick, and then return as much as is
+/// available.
+#[cfg(feature = "io")]
+impl embedded_io::Read for Uart {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let mut count = 0;
+        while count == 0 {
+            while let Some(c) = self.poll_in() {
+                buf[count] = c;
+                count += 1;
+                if count == buf.len() {
+                    break;
+                }
+            }
+            if count == 0 {
+                sleep(Duration::from_ticks(1));
+            }
+        }
+        Ok(count)
+    }
+}
+
+#[cfg(feature = "io")]
+impl embedded_io::Write for Uart {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.send(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
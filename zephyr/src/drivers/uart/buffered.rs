@@ -0,0 +1,518 @@
+// Copyright (c) 2024 Linaro LTD
+// SPDX-License-Identifier: Apache-2.0
+
+//! A UART with buffered reception.
+//!
+//! Received data is collected into a ring buffer of `RX` bytes from the UART's interrupts.  Data
+//! arriving while the ring is full is dropped.  Writes hand the caller's buffer to the interrupt
+//! handlers, and complete once it has all been sent.
+//!
+//! Two backends are supported, and the one to use is chosen when the UART is attached:
+//!
+//! - [`attach_irq`](BufferedUart::attach_irq) uses the interrupt driven API, moving data to and
+//!   from the UART's FIFO in the interrupt handler.  Needs `CONFIG_UART_INTERRUPT_DRIVEN`.
+//! - [`attach_async`](BufferedUart::attach_async) uses the async API, which generally uses DMA,
+//!   receiving into a pair of small buffers that are copied into the ring.  Needs
+//!   `CONFIG_UART_ASYNC_API`.
+//!
+//! The interrupt handlers need a fixed address to refer to, so a `BufferedUart` is a `static` item,
+//! and the `embedded-io` traits are implemented for a shared reference to it.
+//!
+//! ```ignore
+//! static MODEM: BufferedUart<256> = BufferedUart::new();
+//!
+//! MODEM.attach_irq(Uart::from_dt(&zephyr::devicetree::labels::uart1::NODE)?)?;
+//! let mut modem = &MODEM;
+//! modem.write_all(b"AT\r\n")?;
+//! ```
+
+use core::ffi::c_void;
+use core::fmt;
+
+use super::Uart;
+use crate::device::Device;
+use crate::error::{to_result_void, Error, Result};
+use crate::raw;
+use crate::sync::Semaphore;
+use crate::sys::SpinMutex;
+use crate::time::Forever;
+#[cfg(feature = "io-async")]
+use crate::time::NoWait;
+
+#[cfg(CONFIG_UART_ASYNC_API)]
+use core::cell::UnsafeCell;
+#[cfg(feature = "io-async")]
+use core::future::poll_fn;
+#[cfg(feature = "io-async")]
+use core::task::{Poll, Waker};
+
+/// The size of each of the receive buffers used with the async API.
+#[cfg(CONFIG_UART_ASYNC_API)]
+const DMA_CHUNK: usize = 32;
+
+/// The `SYS_FOREVER_US` timeout of the async API.
+#[cfg(CONFIG_UART_ASYNC_API)]
+const SYS_FOREVER_US: i32 = -1;
+
+/// A fixed size ring buffer of bytes.
+struct Ring<const N: usize> {
+    buf: [u8; N],
+    start: usize,
+    len: usize,
+}
+
+impl<const N: usize> Ring<N> {
+    const fn new() -> Ring<N> {
+        Ring { buf: [0; N], start: 0, len: 0 }
+    }
+
+    /// Add as much of `data` as fits.
+    fn push(&mut self, data: &[u8]) {
+        for &b in data.iter().take(N - self.len) {
+            self.buf[(self.start + self.len) % N] = b;
+            self.len += 1;
+        }
+    }
+
+    /// Remove as much data as fits into `out`, returning the number of bytes.
+    fn pop(&mut self, out: &mut [u8]) -> usize {
+        let count = out.len().min(self.len);
+        for b in &mut out[..count] {
+            *b = self.buf[self.start];
+            self.start = (self.start + 1) % N;
+        }
+        self.len -= count;
+        count
+    }
+}
+
+/// Which API the UART is driven with.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Backend {
+    #[cfg(CONFIG_UART_INTERRUPT_DRIVEN)]
+    Irq,
+    #[cfg(CONFIG_UART_ASYNC_API)]
+    Async,
+}
+
+/// The state shared with the interrupt handlers.
+struct State<const RX: usize> {
+    attached: Option<(Device, Backend)>,
+    rx: Ring<RX>,
+    /// The remainder of the buffer being sent, if a write is in progress.
+    tx: Option<(*const u8, usize)>,
+    /// The async receive buffer to give to the driver next.
+    #[cfg(CONFIG_UART_ASYNC_API)]
+    next_dma: usize,
+    /// The receive timeout, for restarting reception with the async API.
+    #[cfg(CONFIG_UART_ASYNC_API)]
+    rx_timeout: i32,
+    #[cfg(feature = "io-async")]
+    rx_waker: Option<Waker>,
+    #[cfg(feature = "io-async")]
+    tx_waker: Option<Waker>,
+}
+
+// The tx pointer is only used while the writer that owns the buffer is waiting for it.
+unsafe impl<const RX: usize> Send for State<RX> {}
+
+/// A UART with buffered reception, with an `RX` byte receive buffer.
+pub struct BufferedUart<const RX: usize> {
+    state: SpinMutex<State<RX>>,
+    /// Given whenever data is received, to wake blocked readers.
+    rx_ready: Semaphore,
+    /// Given when a write completes.
+    tx_done: Semaphore,
+    /// The buffers the driver receives into, with the async API.
+    #[cfg(CONFIG_UART_ASYNC_API)]
+    dma: UnsafeCell<[[u8; DMA_CHUNK]; 2]>,
+}
+
+// The receive buffers are only touched by the driver, and by its callback.
+#[cfg(CONFIG_UART_ASYNC_API)]
+unsafe impl<const RX: usize> Sync for BufferedUart<RX> {}
+
+impl<const RX: usize> BufferedUart<RX> {
+    /// Create a new buffered UART.  It must be attached to a UART before use.
+    pub const fn new() -> BufferedUart<RX> {
+        assert!(RX > 0, "the receive buffer must not be empty");
+        BufferedUart {
+            state: SpinMutex::new(State {
+                attached: None,
+                rx: Ring::new(),
+                tx: None,
+                #[cfg(CONFIG_UART_ASYNC_API)]
+                next_dma: 1,
+                #[cfg(CONFIG_UART_ASYNC_API)]
+                rx_timeout: 0,
+                #[cfg(feature = "io-async")]
+                rx_waker: None,
+                #[cfg(feature = "io-async")]
+                tx_waker: None,
+            }),
+            rx_ready: Semaphore::new(0, 1),
+            tx_done: Semaphore::new(0, 1),
+            #[cfg(CONFIG_UART_ASYNC_API)]
+            dma: UnsafeCell::new([[0; DMA_CHUNK]; 2]),
+        }
+    }
+
+    /// Claim the UART for this buffer.  Fails with `EBUSY` if one is already attached.
+    fn attach(&self, uart: &Uart, backend: Backend) -> Result<Device> {
+        let mut state = self.state.lock();
+        if state.attached.is_some() {
+            return Err(Error(raw::EBUSY));
+        }
+        state.attached = Some((uart.device(), backend));
+        Ok(uart.device())
+    }
+
+    /// Undo [`attach`](Self::attach) if setting up the UART failed.
+    fn detach_on_error(&self, result: Result<()>) -> Result<()> {
+        if result.is_err() {
+            self.state.lock().attached = None;
+        }
+        result
+    }
+
+    /// Start using the UART with the interrupt driven API, and start receiving.  Fails with
+    /// `EBUSY` if a UART is already attached.
+    #[cfg(CONFIG_UART_INTERRUPT_DRIVEN)]
+    pub fn attach_irq(&'static self, uart: Uart) -> Result<()> {
+        let dev = self.attach(&uart, Backend::Irq)?;
+        let result = to_result_void(unsafe {
+            raw::uart_irq_callback_user_data_set(
+                dev.as_raw(),
+                Some(irq_callback::<RX>),
+                self as *const Self as *mut c_void,
+            )
+        });
+        if result.is_ok() {
+            unsafe { raw::uart_irq_rx_enable(dev.as_raw()) };
+        }
+        self.detach_on_error(result)
+    }
+
+    /// Start using the UART with the async API, and start receiving.  A receive buffer is handed
+    /// over to the ring after `rx_timeout` microseconds without new data, or when it fills.  Fails
+    /// with `EBUSY` if a UART is already attached.
+    #[cfg(CONFIG_UART_ASYNC_API)]
+    pub fn attach_async(&'static self, uart: Uart, rx_timeout: i32) -> Result<()> {
+        let dev = self.attach(&uart, Backend::Async)?;
+        self.state.lock().rx_timeout = rx_timeout;
+        let result = to_result_void(unsafe {
+            raw::uart_callback_set(
+                dev.as_raw(),
+                Some(async_callback::<RX>),
+                self as *const Self as *mut c_void,
+            )
+        });
+        let result = result.and_then(|_| self.start_rx(dev));
+        self.detach_on_error(result)
+    }
+
+    #[cfg(CONFIG_UART_ASYNC_API)]
+    fn dma_buf(&self, index: usize) -> *mut u8 {
+        unsafe { (*self.dma.get())[index].as_mut_ptr() }
+    }
+
+    /// Start async reception into the first receive buffer.
+    #[cfg(CONFIG_UART_ASYNC_API)]
+    fn start_rx(&self, dev: Device) -> Result<()> {
+        let rx_timeout = {
+            let mut state = self.state.lock();
+            state.next_dma = 1;
+            state.rx_timeout
+        };
+        to_result_void(unsafe {
+            raw::uart_rx_enable(dev.as_raw(), self.dma_buf(0), DMA_CHUNK, rx_timeout)
+        })
+    }
+
+    /// The number of bytes waiting to be read.
+    pub fn available(&self) -> usize {
+        self.state.lock().rx.len
+    }
+
+    /// Read whatever is available, without waiting.
+    pub fn try_read(&self, buf: &mut [u8]) -> usize {
+        self.state.lock().rx.pop(buf)
+    }
+
+    /// Read into `buf`, waiting until at least one byte is available.  Returns the number of bytes
+    /// read.
+    pub fn read(&self, buf: &mut [u8]) -> Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            let count = self.try_read(buf);
+            if count > 0 {
+                return Ok(count);
+            }
+            self.rx_ready.take(Forever)?;
+        }
+    }
+
+    /// Send all of `data`, waiting for it to complete.  Fails with `EBUSY` if another write is in
+    /// progress, as only one writer is supported at a time.
+    pub fn write(&self, data: &[u8]) -> Result<()> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        self.start_write(data)?;
+        self.tx_done.take(Forever)
+    }
+
+    /// Hand `data` to the interrupt handlers.  The caller must keep it valid until the write is no
+    /// longer in progress.
+    fn start_write(&self, data: &[u8]) -> Result<()> {
+        let (dev, backend) = {
+            let mut state = self.state.lock();
+            let attached = state.attached.ok_or(Error(raw::ENODEV))?;
+            if state.tx.is_some() {
+                return Err(Error(raw::EBUSY));
+            }
+            state.tx = Some((data.as_ptr(), data.len()));
+            // Only the writer that claimed the transmitter clears the completion, and the
+            // handlers can't complete this write until the transmitter is started below, so the
+            // give for this write is never lost.
+            self.tx_done.reset();
+            attached
+        };
+
+        match backend {
+            #[cfg(CONFIG_UART_INTERRUPT_DRIVEN)]
+            Backend::Irq => {
+                unsafe { raw::uart_irq_tx_enable(dev.as_raw()) };
+                Ok(())
+            }
+            #[cfg(CONFIG_UART_ASYNC_API)]
+            Backend::Async => {
+                let result = to_result_void(unsafe {
+                    raw::uart_tx(dev.as_raw(), data.as_ptr(), data.len(), SYS_FOREVER_US)
+                });
+                if result.is_err() {
+                    self.state.lock().tx = None;
+                }
+                result
+            }
+        }
+    }
+
+    /// Receive data, and wake readers.  Called from the interrupt handlers.
+    fn received(&self, data: &[u8]) {
+        #[cfg_attr(not(feature = "io-async"), allow(unused_mut))]
+        let mut state = self.state.lock();
+        state.rx.push(data);
+        #[cfg(feature = "io-async")]
+        if let Some(waker) = state.rx_waker.take() {
+            waker.wake();
+        }
+        drop(state);
+        self.rx_ready.give();
+    }
+
+    /// The write has completed.  Called from the interrupt handlers, with the state locked.
+    fn sent(&self, state: &mut State<RX>) {
+        state.tx = None;
+        #[cfg(feature = "io-async")]
+        if let Some(waker) = state.tx_waker.take() {
+            waker.wake();
+        }
+        self.tx_done.give();
+    }
+}
+
+impl<const RX: usize> Default for BufferedUart<RX> {
+    fn default() -> Self {
+        BufferedUart::new()
+    }
+}
+
+impl<const RX: usize> fmt::Debug for BufferedUart<RX> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "BufferedUart {{ .. }}")
+    }
+}
+
+#[cfg(CONFIG_UART_INTERRUPT_DRIVEN)]
+unsafe extern "C" fn irq_callback<const RX: usize>(
+    dev: *const raw::device,
+    user_data: *mut c_void,
+) {
+    let this = &*(user_data as *const BufferedUart<RX>);
+
+    while raw::uart_irq_update(dev) > 0 && raw::uart_irq_is_pending(dev) > 0 {
+        if raw::uart_irq_rx_ready(dev) > 0 {
+            let mut chunk = [0u8; 16];
+            let count = raw::uart_fifo_read(dev, chunk.as_mut_ptr(), chunk.len() as i32);
+            if count > 0 {
+                this.received(&chunk[..count as usize]);
+            }
+        }
+
+        if raw::uart_irq_tx_ready(dev) > 0 {
+            let mut state = this.state.lock();
+            match state.tx {
+                Some((data, len)) => {
+                    let count = raw::uart_fifo_fill(dev, data, len as i32).max(0) as usize;
+                    if count < len {
+                        state.tx = Some((data.add(count), len - count));
+                    } else {
+                        raw::uart_irq_tx_disable(dev);
+                        this.sent(&mut state);
+                    }
+                }
+                None => raw::uart_irq_tx_disable(dev),
+            }
+        }
+    }
+}
+
+#[cfg(CONFIG_UART_ASYNC_API)]
+unsafe extern "C" fn async_callback<const RX: usize>(
+    dev: *const raw::device,
+    event: *mut raw::uart_event,
+    user_data: *mut c_void,
+) {
+    let this = &*(user_data as *const BufferedUart<RX>);
+    let event = &*event;
+
+    match event.type_ {
+        raw::uart_event_type_UART_RX_RDY => {
+            let rx = &event.data.rx;
+            this.received(core::slice::from_raw_parts(rx.buf.add(rx.offset), rx.len));
+        }
+        raw::uart_event_type_UART_RX_BUF_REQUEST => {
+            let next = {
+                let mut state = this.state.lock();
+                let next = state.next_dma;
+                state.next_dma = 1 - next;
+                next
+            };
+            raw::uart_rx_buf_rsp(dev, this.dma_buf(next), DMA_CHUNK);
+        }
+        raw::uart_event_type_UART_RX_DISABLED => {
+            // Reception stops after errors, or a break, so restart it.
+            if let Some((dev, _)) = this.state.lock().attached {
+                let _ = this.start_rx(dev);
+            }
+        }
+        raw::uart_event_type_UART_TX_DONE | raw::uart_event_type_UART_TX_ABORTED => {
+            let mut state = this.state.lock();
+            this.sent(&mut state);
+        }
+        _ => (),
+    }
+}
+
+#[cfg(feature = "io")]
+impl<const RX: usize> embedded_io::ErrorType for &BufferedUart<RX> {
+    type Error = Error;
+}
+
+#[cfg(feature = "io")]
+impl<const RX: usize> embedded_io::Read for &BufferedUart<RX> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        BufferedUart::read(self, buf)
+    }
+}
+
+#[cfg(feature = "io")]
+impl<const RX: usize> embedded_io::Write for &BufferedUart<RX> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        BufferedUart::write(self, buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "io-async")]
+impl<const RX: usize> embedded_io_async::Read for &BufferedUart<RX> {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        poll_fn(|cx| {
+            let mut state = self.state.lock();
+            match state.rx.pop(buf) {
+                0 => {
+                    state.rx_waker = Some(cx.waker().clone());
+                    Poll::Pending
+                }
+                count => Poll::Ready(Ok(count)),
+            }
+        })
+        .await
+    }
+}
+
+#[cfg(feature = "io-async")]
+impl<const RX: usize> embedded_io_async::Write for &BufferedUart<RX> {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        self.start_write(buf)?;
+        let guard = WriteGuard { uart: *self };
+        poll_fn(|cx| {
+            let mut state = guard.uart.state.lock();
+            if state.tx.is_none() {
+                return Poll::Ready(());
+            }
+            state.tx_waker = Some(cx.waker().clone());
+            Poll::Pending
+        })
+        .await;
+        // Completion was reported through the waker, so consume the give meant for blocking
+        // writers.
+        let _ = self.tx_done.take(NoWait);
+        Ok(buf.len())
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Stops a write in progress if the writing future is dropped, so that the interrupt handlers
+/// are done with the buffer before it goes away.
+#[cfg(feature = "io-async")]
+struct WriteGuard<'a, const RX: usize> {
+    uart: &'a BufferedUart<RX>,
+}
+
+#[cfg(feature = "io-async")]
+impl<const RX: usize> Drop for WriteGuard<'_, RX> {
+    fn drop(&mut self) {
+        let mut state = self.uart.state.lock();
+        if state.tx.is_none() {
+            return;
+        }
+        let (dev, backend) = match state.attached {
+            Some(attached) => attached,
+            None => return,
+        };
+        match backend {
+            #[cfg(CONFIG_UART_INTERRUPT_DRIVEN)]
+            Backend::Irq => {
+                // With the lock held, the handler can't be using the buffer.
+                unsafe { raw::uart_irq_tx_disable(dev.as_raw()) };
+                state.tx = None;
+            }
+            #[cfg(CONFIG_UART_ASYNC_API)]
+            Backend::Async => {
+                // The driver is using the buffer until it reports the abort.
+                drop(state);
+                unsafe { raw::uart_tx_abort(dev.as_raw()) };
+                while self.uart.state.lock().tx.is_some() {
+                    crate::time::yield_now();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,42 @@
+// Copyright (c) 2024 Linaro LTD
+// SPDX-License-Identifier: Apache-2.0
+
+//! Zephyr errors
+//!
+//! Most Zephyr calls return an `int`, with negative values being a negated errno value, and zero
+//! or positive values indicating success.  This module provides a wrapper for these errno values,
+//! and helpers to convert the raw return values into a Rust `Result`.
+
+use core::ffi::c_int;
+use core::fmt;
+
+/// A Zephyr error.  Holds the (positive) errno value that the call returned.  The errno constants
+/// are available as `zephyr::raw::EINVAL` and so on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Error(pub u32);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "zephyr error errno:{}", self.0)
+    }
+}
+
+/// Wraps a value with a possible Zephyr error.
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// Map a return result from Zephyr into a Result.  Negative values are errors, anything else is
+/// returned as the success value.
+#[inline(always)]
+pub fn to_result(code: c_int) -> Result<c_int> {
+    if code < 0 {
+        Err(Error(code.unsigned_abs()))
+    } else {
+        Ok(code)
+    }
+}
+
+/// Map a return result from Zephyr, where the success value carries no information.
+#[inline(always)]
+pub fn to_result_void(code: c_int) -> Result<()> {
+    to_result(code).map(|_| ())
+}
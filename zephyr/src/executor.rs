@@ -0,0 +1,193 @@
+// Copyright (c) 2024 Linaro LTD
+// SPDX-License-Identifier: Apache-2.0
+
+//! A simple async executor.
+//!
+//! An [`Executor`] runs a fixed set of futures on the thread that calls it, polling each one only
+//! after it has been woken.  While no future can make progress, the thread blocks in `k_poll`,
+//! waiting on a `k_poll_signal`.  Wakers raise that signal with `k_poll_signal_raise`, so they
+//! can be used from interrupt handlers.
+//!
+//! The executor needs no allocation.  The futures are pinned by the caller, typically on the
+//! stack, and the executor itself is a `static` item, so that wakers remain valid even if they
+//! outlive a run.  This module requires `CONFIG_POLL`.
+//!
+//! The timer futures, [`sleep`] and [`sleep_until`], are in [`timer`](crate::timer), and are
+//! re-exported here.
+//!
+//! ```ignore
+//! static EXECUTOR: Executor<2> = Executor::new();
+//!
+//! async fn blink() {
+//!     loop {
+//!         // ...
+//!         sleep(Duration::millis(500)).await;
+//!     }
+//! }
+//!
+//! let a = pin!(blink());
+//! let b = pin!(other_task());
+//! EXECUTOR.run([a, b])?;
+//! ```
+
+use core::ffi::c_void;
+use core::future::Future;
+use core::mem;
+use core::pin::{pin, Pin};
+use core::ptr;
+use core::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+use core::task::{Context, RawWaker, RawWakerVTable, Waker};
+
+use crate::error::{Error, Result};
+use crate::object::ZephyrObject;
+use crate::raw::{self, k_poll_signal};
+use crate::sys::{SpinMutex, K_FOREVER};
+
+pub use crate::timer::{sleep, sleep_until, Sleep};
+
+/// The per-task state shared with the task's waker.
+struct Slot {
+    /// Set by the waker, and cleared before the task is polled.
+    woken: AtomicBool,
+    /// The signal of the executor.  Set when a run starts.
+    signal: AtomicPtr<k_poll_signal>,
+}
+
+impl Slot {
+    const fn new() -> Slot {
+        Slot {
+            woken: AtomicBool::new(false),
+            signal: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+
+    fn wake(&self) {
+        self.woken.store(true, Ordering::Release);
+        let signal = self.signal.load(Ordering::Acquire);
+        if !signal.is_null() {
+            unsafe { raw::k_poll_signal_raise(signal, 0) };
+        }
+    }
+}
+
+static VTABLE: RawWakerVTable =
+    RawWakerVTable::new(waker_clone, waker_wake, waker_wake, waker_drop);
+
+unsafe fn waker_clone(data: *const ()) -> RawWaker {
+    RawWaker::new(data, &VTABLE)
+}
+
+unsafe fn waker_wake(data: *const ()) {
+    (*(data as *const Slot)).wake();
+}
+
+unsafe fn waker_drop(_data: *const ()) {}
+
+/// An executor that can run up to `N` futures at a time.
+pub struct Executor<const N: usize> {
+    signal: ZephyrObject<k_poll_signal>,
+    slots: [Slot; N],
+    running: SpinMutex<bool>,
+}
+
+impl<const N: usize> Executor<N> {
+    /// Create a new executor.
+    pub const fn new() -> Executor<N> {
+        assert!(N > 0, "an executor needs at least one task slot");
+        Executor {
+            signal: ZephyrObject::new(),
+            slots: [const { Slot::new() }; N],
+            running: SpinMutex::new(false),
+        }
+    }
+
+    /// Run all of the given futures until they have completed.  Fails with `EBUSY` if the
+    /// executor is already running on another thread.
+    pub fn run(&'static self, mut tasks: [Pin<&mut dyn Future<Output = ()>>; N]) -> Result<()> {
+        self.run_tasks(&mut tasks)
+    }
+
+    /// Run a single future until it completes, returning its output.  Fails with `EBUSY` if the
+    /// executor is already running on another thread.
+    pub fn block_on<F: Future>(&'static self, future: F) -> Result<F::Output> {
+        let mut output = None;
+        {
+            let task = pin!(async { output = Some(future.await) });
+            self.run_tasks(&mut [task])?;
+        }
+        Ok(output.expect("future did not complete"))
+    }
+
+    fn run_tasks(&'static self, tasks: &mut [Pin<&mut dyn Future<Output = ()>>]) -> Result<()> {
+        {
+            let mut running = self.running.lock();
+            if *running {
+                return Err(Error(raw::EBUSY));
+            }
+            *running = true;
+        }
+
+        let signal = self.signal.get(|s| unsafe {
+            raw::k_poll_signal_init(s);
+        });
+
+        // Every task is polled once to start.
+        for slot in &self.slots[..tasks.len()] {
+            slot.signal.store(signal, Ordering::Release);
+            slot.woken.store(true, Ordering::Release);
+        }
+
+        let mut done = [false; N];
+        let mut remaining = tasks.len();
+        while remaining > 0 {
+            // Reset before looking at the tasks.  Any wake after this raises the signal again, and
+            // the `k_poll` below will return immediately.
+            unsafe { raw::k_poll_signal_reset(signal) };
+
+            for (index, task) in tasks.iter_mut().enumerate() {
+                let slot = &self.slots[index];
+                // Clear and test in one step, so that a wake from another CPU can't be lost, and
+                // anything done before it is visible to the poll.
+                if done[index] || !slot.woken.swap(false, Ordering::AcqRel) {
+                    continue;
+                }
+
+                let waker = unsafe {
+                    Waker::from_raw(RawWaker::new(slot as *const Slot as *const (), &VTABLE))
+                };
+                let mut cx = Context::from_waker(&waker);
+                if task.as_mut().poll(&mut cx).is_ready() {
+                    done[index] = true;
+                    remaining -= 1;
+                }
+            }
+
+            if remaining > 0 {
+                park(signal);
+            }
+        }
+
+        *self.running.lock() = false;
+        Ok(())
+    }
+}
+
+impl<const N: usize> Default for Executor<N> {
+    fn default() -> Self {
+        Executor::new()
+    }
+}
+
+/// Block until the signal is raised.
+fn park(signal: *mut k_poll_signal) {
+    unsafe {
+        let mut event: raw::k_poll_event = mem::zeroed();
+        raw::k_poll_event_init(
+            &mut event,
+            raw::ZR_POLL_TYPE_SIGNAL,
+            raw::ZR_POLL_MODE_NOTIFY_ONLY,
+            signal as *mut c_void,
+        );
+        raw::k_poll(&mut event, 1, K_FOREVER);
+    }
+}
@@ -0,0 +1,68 @@
+// Copyright (c) 2024 Linaro LTD
+// SPDX-License-Identifier: Apache-2.0
+
+//! Implementations of the `embedded-hal` 1.0 traits.
+//!
+//! These allow drivers written against `embedded-hal` to be used with Zephyr's drivers.  Each group
+//! of traits is enabled by a cargo feature of the `zephyr` crate:
+//!
+//! - `hal-gpio`: `OutputPin`, `StatefulOutputPin` and `InputPin` for
+//!   [`GpioPin`](crate::drivers::gpio::GpioPin).
+//! - `hal-i2c`: `I2c` for [`I2c`](crate::drivers::i2c::I2c).
+//! - `hal-spi`: `SpiDevice` for [`Spi`](crate::drivers::spi::Spi).
+//! - `hal-delay`: `DelayNs` for [`Delay`].
+//!
+//! The driver based traits also need the driver class to be enabled in the Zephyr configuration.
+//!
+//! Adding the `hal-async` feature also implements the matching `embedded-hal-async` traits for
+//! each of the enabled groups.  GPIO `Wait` uses the pin's interrupt, I2C needs
+//! `CONFIG_I2C_CALLBACK` and SPI needs `CONFIG_SPI_ASYNC`, to use the callback based transfers, and
+//! delays wait on a `k_timer`.  These are woken from interrupt context, and so work with any
+//! executor whose wakers can be used there, such as the one in [`executor`](crate::executor).
+//!
+//! All of these report errors as a Zephyr [`Error`], which always has a kind of `Other`, as Zephyr
+//! drivers don't distinguish the causes in a consistent way.  The errno value is still available
+//! from the error itself.
+
+#[cfg(any(feature = "hal-gpio", feature = "hal-i2c", feature = "hal-spi"))]
+use crate::error::Error;
+
+#[cfg(all(
+    feature = "hal-async",
+    any(all(feature = "hal-i2c", CONFIG_I2C_CALLBACK), all(feature = "hal-spi", CONFIG_SPI_ASYNC)),
+))]
+mod completion;
+#[cfg(feature = "hal-delay")]
+mod delay;
+#[cfg(all(feature = "hal-gpio", CONFIG_GPIO))]
+mod digital;
+#[cfg(all(feature = "hal-i2c", CONFIG_I2C))]
+mod i2c;
+#[cfg(all(feature = "hal-spi", CONFIG_SPI))]
+mod spi;
+#[cfg(all(feature = "hal-async", feature = "hal-gpio", CONFIG_GPIO))]
+mod wait;
+
+#[cfg(feature = "hal-delay")]
+pub use delay::Delay;
+
+#[cfg(feature = "hal-gpio")]
+impl embedded_hal::digital::Error for Error {
+    fn kind(&self) -> embedded_hal::digital::ErrorKind {
+        embedded_hal::digital::ErrorKind::Other
+    }
+}
+
+#[cfg(feature = "hal-i2c")]
+impl embedded_hal::i2c::Error for Error {
+    fn kind(&self) -> embedded_hal::i2c::ErrorKind {
+        embedded_hal::i2c::ErrorKind::Other
+    }
+}
+
+#[cfg(feature = "hal-spi")]
+impl embedded_hal::spi::Error for Error {
+    fn kind(&self) -> embedded_hal::spi::ErrorKind {
+        embedded_hal::spi::ErrorKind::Other
+    }
+}
@@ -0,0 +1,116 @@
+// Copyright (c) 2024 Linaro LTD
+// SPDX-License-Identifier: Apache-2.0
+
+//! Completion of asynchronous driver operations.
+//!
+//! The callback based driver APIs (`i2c_transfer_cb`, `spi_transceive_cb`) take a callback and a
+//! user data pointer.  [`completion_callback`] with a pointer to a [`Completion`] records the
+//! result and wakes the task waiting on it.
+
+use core::cell::UnsafeCell;
+use core::ffi::{c_int, c_void};
+use core::future::Future;
+use core::mem;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+
+use crate::error::{to_result_void, Result};
+use crate::raw::{self, k_sem};
+use crate::sys::{SpinMutex, K_FOREVER};
+
+/// The state shared between a pending operation and its callback.
+///
+/// Everything, including the result, is behind the lock.  Once the waiter has seen the result
+/// under the lock, the callback is done with the Completion, and it can be freed.
+pub(crate) struct Completion {
+    state: SpinMutex<State>,
+    /// Given by the callback if the waiter was dropped first, and is blocked on it.  This is only
+    /// initialized then, once the Completion can no longer move.
+    done: UnsafeCell<k_sem>,
+}
+
+struct State {
+    result: Option<c_int>,
+    waker: Option<Waker>,
+    /// Set once `done` is initialized, and the waiter is about to block on it.
+    blocked: bool,
+}
+
+impl Completion {
+    pub(crate) const fn new() -> Completion {
+        Completion {
+            state: SpinMutex::new(State { result: None, waker: None, blocked: false }),
+            done: UnsafeCell::new(unsafe { mem::zeroed() }),
+        }
+    }
+
+    /// The user data to give along with [`completion_callback`].
+    pub(crate) fn as_user_data(&self) -> *mut c_void {
+        self as *const Completion as *mut c_void
+    }
+
+    /// Wait for the callback, once the operation has been started.
+    ///
+    /// The driver holds on to the buffers, and to this Completion, until the operation finishes.
+    /// If the returned future is dropped early, it blocks the thread until then, so these must be
+    /// declared before it, and the future must not be leaked.
+    pub(crate) fn wait(&self) -> Wait<'_> {
+        Wait { completion: self }
+    }
+}
+
+/// The future returned by [`Completion::wait`].
+pub(crate) struct Wait<'a> {
+    completion: &'a Completion,
+}
+
+impl Future for Wait<'_> {
+    type Output = Result<()>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        let mut state = self.completion.state.lock();
+        match state.result {
+            Some(result) => Poll::Ready(to_result_void(result)),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl Drop for Wait<'_> {
+    fn drop(&mut self) {
+        let done = self.completion.done.get();
+        {
+            let mut state = self.completion.state.lock();
+            if state.result.is_some() {
+                return;
+            }
+            unsafe { raw::k_sem_init(done, 0, 1) };
+            state.blocked = true;
+        }
+        unsafe { raw::k_sem_take(done, K_FOREVER) };
+    }
+}
+
+/// The driver callback for operations using a [`Completion`].
+pub(crate) unsafe extern "C" fn completion_callback(
+    _dev: *const raw::device,
+    result: c_int,
+    data: *mut c_void,
+) {
+    let completion = &*(data as *const Completion);
+    let (waker, blocked) = {
+        let mut state = completion.state.lock();
+        state.result = Some(result);
+        (state.waker.take(), state.blocked)
+    };
+    if blocked {
+        // The waiter keeps the Completion until this is given.
+        raw::k_sem_give(completion.done.get());
+    } else if let Some(waker) = waker {
+        // The Completion may be gone by now.
+        waker.wake();
+    }
+}
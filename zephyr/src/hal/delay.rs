@@ -0,0 +1,77 @@
+// Copyright (c) 2024 Linaro LTD
+// SPDX-License-Identifier: Apache-2.0
+
+//! `embedded-hal` delays.
+//!
+//! With the `hal-async` feature, [`Delay`] also implements the `embedded-hal-async` `DelayNs`,
+//! waiting on a `k_timer` for the longer delays.
+
+use embedded_hal::delay::DelayNs;
+
+use crate::time::{busy_wait, sleep, Duration, Tick, SYS_FREQUENCY};
+#[cfg(feature = "hal-async")]
+use crate::timer;
+
+/// The length of a system tick, in nanoseconds.
+const TICK_NS: u32 = 1_000_000_000 / SYS_FREQUENCY;
+
+/// A provider of `DelayNs`, both blocking and, with `hal-async`, async.
+///
+/// Delays shorter than a system tick busy wait with `k_busy_wait`, as the kernel can't time these
+/// with sleeps.  Longer delays put the thread to sleep, allowing other threads to run.  Either
+/// way, the delay is at least as long as requested, but a sleep may be up to a tick longer.  As
+/// they sleep, longer delays must not be used from interrupt context.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Delay;
+
+impl DelayNs for Delay {
+    fn delay_ns(&mut self, ns: u32) {
+        if ns < TICK_NS {
+            busy_wait(ns.div_ceil(1000));
+        } else {
+            sleep(Duration::nanos_at_least(Tick::from(ns)));
+        }
+    }
+
+    fn delay_us(&mut self, us: u32) {
+        if us < TICK_NS / 1000 {
+            busy_wait(us);
+        } else {
+            sleep(Duration::micros_at_least(Tick::from(us)));
+        }
+    }
+
+    fn delay_ms(&mut self, ms: u32) {
+        sleep(Duration::millis_at_least(Tick::from(ms)));
+    }
+}
+
+#[cfg(feature = "hal-async")]
+impl embedded_hal_async::delay::DelayNs for Delay {
+    async fn delay_ns(&mut self, ns: u32) {
+        if ns < TICK_NS {
+            busy_wait(ns.div_ceil(1000));
+        } else {
+            sleep_at_least(Duration::nanos_at_least(Tick::from(ns))).await;
+        }
+    }
+
+    async fn delay_us(&mut self, us: u32) {
+        if us < TICK_NS / 1000 {
+            busy_wait(us);
+        } else {
+            sleep_at_least(Duration::micros_at_least(Tick::from(us))).await;
+        }
+    }
+
+    async fn delay_ms(&mut self, ms: u32) {
+        sleep_at_least(Duration::millis_at_least(Tick::from(ms))).await;
+    }
+}
+
+/// Wait for at least `duration`.  The timer future sleeps until a deadline computed from the
+/// current tick, part of which has already passed, so one more tick is needed to cover it.
+#[cfg(feature = "hal-async")]
+async fn sleep_at_least(duration: Duration) {
+    timer::sleep(duration + Duration::from_ticks(1)).await;
+}
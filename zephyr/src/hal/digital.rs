@@ -0,0 +1,52 @@
+// Copyright (c) 2024 Linaro LTD
+// SPDX-License-Identifier: Apache-2.0
+
+//! `embedded-hal` digital traits for GPIO pins.
+//!
+//! As with the rest of the GPIO API, levels are logical: "high" means the pin is active, taking the
+//! devicetree polarity into account.
+
+use embedded_hal::digital::{ErrorType, InputPin, OutputPin, StatefulOutputPin};
+
+use crate::drivers::gpio::GpioPin;
+use crate::error::{Error, Result};
+
+impl ErrorType for GpioPin {
+    type Error = Error;
+}
+
+impl OutputPin for GpioPin {
+    fn set_low(&mut self) -> Result<()> {
+        self.set(false)
+    }
+
+    fn set_high(&mut self) -> Result<()> {
+        self.set(true)
+    }
+}
+
+/// The level is read back from the pin, so the pin must be configured with both `GPIO_INPUT` and
+/// `GPIO_OUTPUT` for this to reflect the output level on most controllers.
+impl StatefulOutputPin for GpioPin {
+    fn is_set_high(&mut self) -> Result<bool> {
+        self.get()
+    }
+
+    fn is_set_low(&mut self) -> Result<bool> {
+        self.get().map(|value| !value)
+    }
+
+    fn toggle(&mut self) -> Result<()> {
+        GpioPin::toggle(self)
+    }
+}
+
+impl InputPin for GpioPin {
+    fn is_high(&mut self) -> Result<bool> {
+        self.get()
+    }
+
+    fn is_low(&mut self) -> Result<bool> {
+        self.get().map(|value| !value)
+    }
+}
@@ -0,0 +1,144 @@
+// Copyright (c) 2024 Linaro LTD
+// SPDX-License-Identifier: Apache-2.0
+
+//! `embedded-hal` I2C trait for I2C controllers.
+//!
+//! A transaction is performed as a single `i2c_transfer`, so the messages are built on the stack.
+//! This limits a transaction to eight operations, and longer ones fail with `EINVAL`.
+//!
+//! With the `hal-async` feature, and `CONFIG_I2C_CALLBACK`, the `embedded-hal-async` trait is also
+//! implemented, using `i2c_transfer_cb`.
+
+use core::mem;
+
+use embedded_hal::i2c::{ErrorType, I2c as HalI2c, Operation, SevenBitAddress, TenBitAddress};
+
+use crate::drivers::i2c::{
+    I2c,
+    Msg,
+    I2C_MSG_ADDR_10_BITS,
+    I2C_MSG_READ,
+    I2C_MSG_RESTART,
+    I2C_MSG_STOP,
+    I2C_MSG_WRITE,
+};
+use crate::error::{Error, Result};
+use crate::raw;
+
+#[cfg(all(feature = "hal-async", CONFIG_I2C_CALLBACK))]
+use super::completion::{completion_callback, Completion};
+#[cfg(all(feature = "hal-async", CONFIG_I2C_CALLBACK))]
+use crate::error::to_result_void;
+
+/// The largest number of operations in a single transaction.
+const MAX_OPERATIONS: usize = 8;
+
+impl ErrorType for I2c {
+    type Error = Error;
+}
+
+impl HalI2c<SevenBitAddress> for I2c {
+    fn transaction(&mut self, address: u8, operations: &mut [Operation<'_>]) -> Result<()> {
+        transaction(self, address as u16, 0, operations)
+    }
+}
+
+impl HalI2c<TenBitAddress> for I2c {
+    fn transaction(&mut self, address: u16, operations: &mut [Operation<'_>]) -> Result<()> {
+        transaction(self, address, I2C_MSG_ADDR_10_BITS, operations)
+    }
+}
+
+/// The messages for a transaction.
+type Msgs = [Msg; MAX_OPERATIONS];
+
+/// Build the messages for a transaction, returning the number used.  Following the `embedded-hal`
+/// contract, adjacent operations in the same direction are joined, with a restart only where the
+/// direction changes, and a stop after the last operation.
+fn build_msgs(msgs: &mut Msgs, extra: u8, operations: &mut [Operation<'_>]) -> Result<usize> {
+    if operations.is_empty() {
+        return Ok(0);
+    }
+    if operations.len() > MAX_OPERATIONS {
+        return Err(Error(raw::EINVAL));
+    }
+
+    let last = operations.len() - 1;
+    let mut prev_read = None;
+    for (index, (op, msg)) in operations.iter_mut().zip(msgs.iter_mut()).enumerate() {
+        let (buf, len, read) = match op {
+            Operation::Read(buf) => (buf.as_mut_ptr(), buf.len(), true),
+            Operation::Write(data) => (data.as_ptr() as *mut u8, data.len(), false),
+        };
+        msg.buf = buf;
+        msg.len = len.try_into().map_err(|_| Error(raw::EINVAL))?;
+        msg.flags = extra | if read { I2C_MSG_READ } else { I2C_MSG_WRITE };
+        if prev_read.is_some_and(|prev| prev != read) {
+            msg.flags |= I2C_MSG_RESTART;
+        }
+        if index == last {
+            msg.flags |= I2C_MSG_STOP;
+        }
+        prev_read = Some(read);
+    }
+    Ok(operations.len())
+}
+
+/// Perform the operations as a single transfer.
+fn transaction(
+    i2c: &mut I2c,
+    addr: u16,
+    extra: u8,
+    operations: &mut [Operation<'_>],
+) -> Result<()> {
+    let mut msgs: Msgs = unsafe { mem::zeroed() };
+    let count = build_msgs(&mut msgs, extra, operations)?;
+    if count == 0 {
+        return Ok(());
+    }
+
+    // The buffers of the messages come from the operations, which outlive the transfer.
+    unsafe { i2c.transfer(addr, &mut msgs[..count]) }
+}
+
+#[cfg(all(feature = "hal-async", CONFIG_I2C_CALLBACK))]
+impl embedded_hal_async::i2c::I2c<SevenBitAddress> for I2c {
+    async fn transaction(&mut self, address: u8, operations: &mut [Operation<'_>]) -> Result<()> {
+        transaction_async(self, address as u16, 0, operations).await
+    }
+}
+
+#[cfg(all(feature = "hal-async", CONFIG_I2C_CALLBACK))]
+impl embedded_hal_async::i2c::I2c<TenBitAddress> for I2c {
+    async fn transaction(&mut self, address: u16, operations: &mut [Operation<'_>]) -> Result<()> {
+        transaction_async(self, address, I2C_MSG_ADDR_10_BITS, operations).await
+    }
+}
+
+/// Perform the operations as a single transfer with `i2c_transfer_cb`, waiting for the callback.
+#[cfg(all(feature = "hal-async", CONFIG_I2C_CALLBACK))]
+async fn transaction_async(
+    i2c: &mut I2c,
+    addr: u16,
+    extra: u8,
+    operations: &mut [Operation<'_>],
+) -> Result<()> {
+    let mut msgs: Msgs = unsafe { mem::zeroed() };
+    let count = build_msgs(&mut msgs, extra, operations)?;
+    if count == 0 {
+        return Ok(());
+    }
+
+    let completion = Completion::new();
+    to_result_void(unsafe {
+        raw::i2c_transfer_cb(
+            i2c.as_raw(),
+            msgs.as_mut_ptr(),
+            count as u8,
+            addr,
+            Some(completion_callback),
+            completion.as_user_data(),
+        )
+    })?;
+    completion.wait().await
+}
@@ -0,0 +1,140 @@
+// Copyright (c) 2024 Linaro LTD
+// SPDX-License-Identifier: Apache-2.0
+
+//! `embedded-hal` SPI trait for SPI devices.
+//!
+//! Each operation of a transaction is a separate `spi_transceive`.  When there is more than one,
+//! the chip select and the bus are held between them, and released at the end.
+//!
+//! With the `hal-async` feature, and `CONFIG_SPI_ASYNC`, the `embedded-hal-async` trait is also
+//! implemented, using `spi_transceive_cb`.
+
+use embedded_hal::spi::{ErrorType, Operation, SpiDevice};
+
+use crate::drivers::spi::Spi;
+use crate::error::{Error, Result};
+use crate::raw;
+
+#[cfg(all(feature = "hal-async", CONFIG_SPI_ASYNC))]
+use core::ffi::c_void;
+#[cfg(all(feature = "hal-async", CONFIG_SPI_ASYNC))]
+use core::ptr;
+
+#[cfg(all(feature = "hal-async", CONFIG_SPI_ASYNC))]
+use super::completion::{completion_callback, Completion};
+#[cfg(all(feature = "hal-async", CONFIG_SPI_ASYNC))]
+use crate::error::to_result_void;
+
+impl ErrorType for Spi {
+    type Error = Error;
+}
+
+impl SpiDevice for Spi {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<()> {
+        let hold = operations.len() > 1;
+        if hold {
+            self.set_hold(true);
+        }
+
+        let result = operations.iter_mut().try_for_each(|op| match op {
+            Operation::Read(buf) => self.read(buf),
+            Operation::Write(data) => self.write(data),
+            Operation::Transfer(buf, data) => self.transfer(buf, data),
+            Operation::TransferInPlace(buf) => self.transfer_in_place(buf),
+            Operation::DelayNs(ns) => {
+                busy_wait_ns(*ns);
+                Ok(())
+            }
+        });
+
+        finish(self, hold, result)
+    }
+}
+
+#[cfg(all(feature = "hal-async", CONFIG_SPI_ASYNC))]
+impl embedded_hal_async::spi::SpiDevice for Spi {
+    async fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<()> {
+        let hold = operations.len() > 1;
+        if hold {
+            self.set_hold(true);
+        }
+
+        let mut result = Ok(());
+        for op in operations.iter_mut() {
+            let (tx, rx) = match op {
+                Operation::Read(buf) => (None, Some(spi_buf(buf.as_mut_ptr(), buf.len()))),
+                Operation::Write(data) => {
+                    (Some(spi_buf(data.as_ptr() as *mut u8, data.len())), None)
+                }
+                Operation::Transfer(buf, data) => (
+                    Some(spi_buf(data.as_ptr() as *mut u8, data.len())),
+                    Some(spi_buf(buf.as_mut_ptr(), buf.len())),
+                ),
+                Operation::TransferInPlace(buf) => (
+                    Some(spi_buf(buf.as_mut_ptr(), buf.len())),
+                    Some(spi_buf(buf.as_mut_ptr(), buf.len())),
+                ),
+                Operation::DelayNs(ns) => {
+                    busy_wait_ns(*ns);
+                    continue;
+                }
+            };
+            result = transceive_async(self, tx, rx).await;
+            if result.is_err() {
+                break;
+            }
+        }
+
+        finish(self, hold, result)
+    }
+}
+
+/// Wait for the delay between operations.  These are generally short, so this busy waits.
+fn busy_wait_ns(ns: u32) {
+    crate::time::busy_wait(ns.div_ceil(1000));
+}
+
+/// Release the chip select and the bus if they were held, even after a failure, so the bus isn't
+/// left locked.
+fn finish(spi: &mut Spi, hold: bool, result: Result<()>) -> Result<()> {
+    if hold {
+        let released = spi.release();
+        spi.set_hold(false);
+        result?;
+        return released;
+    }
+    result
+}
+
+#[cfg(all(feature = "hal-async", CONFIG_SPI_ASYNC))]
+fn spi_buf(buf: *mut u8, len: usize) -> raw::spi_buf {
+    raw::spi_buf {
+        buf: buf as *mut c_void,
+        len,
+    }
+}
+
+/// Perform a single transfer with `spi_transceive_cb`, waiting for the callback.
+#[cfg(all(feature = "hal-async", CONFIG_SPI_ASYNC))]
+async fn transceive_async(
+    spi: &mut Spi,
+    tx: Option<raw::spi_buf>,
+    rx: Option<raw::spi_buf>,
+) -> Result<()> {
+    let tx_set = tx.as_ref().map(|buf| raw::spi_buf_set { buffers: buf, count: 1 });
+    let rx_set = rx.as_ref().map(|buf| raw::spi_buf_set { buffers: buf, count: 1 });
+    let spec = spi.as_raw();
+
+    let completion = Completion::new();
+    to_result_void(unsafe {
+        raw::spi_transceive_cb(
+            spec.bus,
+            &spec.config,
+            tx_set.as_ref().map_or(ptr::null(), |set| set),
+            rx_set.as_ref().map_or(ptr::null(), |set| set),
+            Some(completion_callback),
+            completion.as_user_data(),
+        )
+    })?;
+    completion.wait().await
+}
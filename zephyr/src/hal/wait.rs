@@ -0,0 +1,148 @@
+// Copyright (c) 2024 Linaro LTD
+// SPDX-License-Identifier: Apache-2.0
+
+//! `embedded-hal-async` `Wait` trait for GPIO pins.
+//!
+//! Each wait enables the pin's interrupt, and registers a GPIO callback that wakes the task.  The
+//! interrupt is disabled again when the wait finishes, so this should not be combined with other
+//! uses of the pin's interrupt.
+//!
+//! As with the blocking traits, levels are logical, so a rising edge is a change to active.
+
+use core::cell::{Cell, UnsafeCell};
+use core::future::Future;
+use core::marker::PhantomPinned;
+use core::mem;
+use core::pin::{pin, Pin};
+use core::task::{Context, Poll, Waker};
+
+use embedded_hal_async::digital::Wait;
+
+use crate::drivers::gpio::{
+    Flags,
+    GpioPin,
+    GPIO_INT_DISABLE,
+    GPIO_INT_EDGE_BOTH,
+    GPIO_INT_EDGE_TO_ACTIVE,
+    GPIO_INT_EDGE_TO_INACTIVE,
+};
+use crate::error::{to_result_void, Result};
+use crate::raw;
+use crate::sys::SpinMutex;
+
+impl Wait for GpioPin {
+    async fn wait_for_high(&mut self) -> Result<()> {
+        wait_for(self, GPIO_INT_EDGE_TO_ACTIVE, Some(true)).await
+    }
+
+    async fn wait_for_low(&mut self) -> Result<()> {
+        wait_for(self, GPIO_INT_EDGE_TO_INACTIVE, Some(false)).await
+    }
+
+    async fn wait_for_rising_edge(&mut self) -> Result<()> {
+        wait_for(self, GPIO_INT_EDGE_TO_ACTIVE, None).await
+    }
+
+    async fn wait_for_falling_edge(&mut self) -> Result<()> {
+        wait_for(self, GPIO_INT_EDGE_TO_INACTIVE, None).await
+    }
+
+    async fn wait_for_any_edge(&mut self) -> Result<()> {
+        wait_for(self, GPIO_INT_EDGE_BOTH, None).await
+    }
+}
+
+/// Wait for the interrupt given by `flags`.  If `level` is given, the pin is checked once the
+/// interrupt is enabled, and the wait finishes immediately if it is already at that level.
+async fn wait_for(pin: &mut GpioPin, flags: Flags, level: Option<bool>) -> Result<()> {
+    let pin: &GpioPin = pin;
+    let mut wait = pin!(PinWait::new(pin));
+    wait.as_ref().start(flags)?;
+    if let Some(level) = level {
+        if pin.get()? == level {
+            return Ok(());
+        }
+    }
+    wait.as_mut().await;
+    Ok(())
+}
+
+/// A future that completes when a GPIO callback fires.
+#[repr(C)]
+struct PinWait<'a> {
+    // Must be first, as the callback recovers the PinWait from the `gpio_callback` pointer.
+    raw: UnsafeCell<raw::gpio_callback>,
+    // Whether the callback has fired, and the waker to wake when it does.
+    state: SpinMutex<(bool, Option<Waker>)>,
+    pin: &'a GpioPin,
+    added: Cell<bool>,
+    _pinned: PhantomPinned,
+}
+
+impl<'a> PinWait<'a> {
+    fn new(pin: &'a GpioPin) -> PinWait<'a> {
+        PinWait {
+            raw: UnsafeCell::new(unsafe { mem::zeroed() }),
+            state: SpinMutex::new((false, None)),
+            pin,
+            added: Cell::new(false),
+            _pinned: PhantomPinned,
+        }
+    }
+
+    /// Register the callback, and enable the interrupt.
+    fn start(self: Pin<&Self>, flags: Flags) -> Result<()> {
+        let spec = self.pin.as_raw();
+        unsafe {
+            raw::gpio_init_callback(self.raw.get(), Some(pin_wait_callback), 1 << spec.pin);
+            to_result_void(raw::gpio_add_callback_dt(spec, self.raw.get()))?;
+        }
+        self.added.set(true);
+        to_result_void(unsafe { raw::gpio_pin_interrupt_configure_dt(spec, flags) })
+    }
+}
+
+impl Future for PinWait<'_> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = self.state.lock();
+        if state.0 {
+            return Poll::Ready(());
+        }
+        state.1 = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl Drop for PinWait<'_> {
+    fn drop(&mut self) {
+        if self.added.get() {
+            let spec = self.pin.as_raw();
+            unsafe {
+                raw::gpio_pin_interrupt_configure_dt(spec, GPIO_INT_DISABLE);
+                raw::gpio_remove_callback_dt(spec, self.raw.get());
+            }
+            // Neither of these waits for a callback already running on another CPU.  The callback
+            // only uses this PinWait with the state locked, so taking the lock waits for it to
+            // finish.
+            drop(self.state.lock());
+        }
+    }
+}
+
+unsafe extern "C" fn pin_wait_callback(
+    _port: *const raw::device,
+    cb: *mut raw::gpio_callback,
+    _pins: raw::gpio_port_pins_t,
+) {
+    let wait = &*(cb as *const PinWait);
+    let waker = {
+        let mut state = wait.state.lock();
+        state.0 = true;
+        state.1.take()
+    };
+    if let Some(waker) = waker {
+        waker.wake();
+    }
+}
@@ -7,10 +7,19 @@
 //! Zephyr.
 
 #![no_std]
-#![allow(unexpected_cfgs)]
 
+pub mod device;
+pub mod devicetree;
+pub mod drivers;
+pub mod error;
+pub mod sync;
 pub mod sys;
+pub mod thread;
 pub mod time;
+pub mod timer;
+pub mod work;
+
+mod object;
 
 // Bring in the generated kconfig module
 include!(concat!(env!("OUT_DIR"), "/kconfig.rs"));
@@ -19,6 +28,32 @@ include!(concat!(env!("OUT_DIR"), "/kconfig.rs"));
 #[cfg(not(CONFIG_RUST))]
 compile_error!("CONFIG_RUST must be set to build Rust in Zephyr");
 
+// The global allocator is provided if it is configured into the build.
+#[cfg(CONFIG_RUST_ALLOC)]
+mod alloc_impl;
+
+// The async executor needs `k_poll`.
+#[cfg(CONFIG_POLL)]
+pub mod executor;
+
+// The `log` crate bridge is enabled by a cargo feature, and needs logging configured into the
+// build.
+#[cfg(all(CONFIG_LOG, feature = "log"))]
+pub mod log;
+
+// The embassy-time driver is enabled by a cargo feature.
+#[cfg(feature = "time-driver")]
+mod time_driver;
+
+// The embedded-hal trait implementations are each enabled by a cargo feature.
+#[cfg(any(
+    feature = "hal-gpio",
+    feature = "hal-i2c",
+    feature = "hal-spi",
+    feature = "hal-delay",
+))]
+pub mod hal;
+
 // Printk is provided if it is configured into the build.
 #[cfg(CONFIG_PRINTK)]
 pub mod printk;
@@ -0,0 +1,301 @@
+// Copyright (c) 2024 Linaro LTD
+// SPDX-License-Identifier: Apache-2.0
+
+//! Rust logging through Zephyr's logging subsystem.
+//!
+//! This provides a logger for the [`log`](::log) crate that forwards messages to the Zephyr `LOG_*`
+//! backends.  Messages are formatted by Rust, and passed to Zephyr as a string, which is copied
+//! into the log message, so with `CONFIG_LOG_MODE_DEFERRED` the caller does not wait for the
+//! console.
+//!
+//! Each Rust module that should have its own log source registers one with
+//! [`log_module_register!`](crate::log_module_register), the analogue of `LOG_MODULE_REGISTER`.
+//! This defines a Zephyr log source, named after the module path, such as `app::sensor`, with its
+//! own level.  The source is used for messages from that module and any modules nested within it,
+//! the most specific registered module winning.  As with sources defined in C, Zephyr's runtime
+//! filtering, and the `log` shell commands, see each of these sources.  Messages from modules
+//! without a source of their own come from the `rust` source, at `CONFIG_RUST_LOG_LEVEL`, with the
+//! module path at the start of the message.
+//!
+//! Zephyr has four levels, so `trace` messages are shown as debug, and are enabled along with
+//! debug messages.
+//!
+//! ```ignore
+//! zephyr::log_module_register!(log::LevelFilter::Debug);
+//!
+//! zephyr::log::set_logger().unwrap();
+//! log::info!("Starting");
+//! ```
+
+#[cfg(CONFIG_LOG_RUNTIME_FILTERING)]
+use core::cell::UnsafeCell;
+use core::ffi::{c_char, CStr};
+use core::fmt::{self, Write};
+use core::mem;
+use core::slice;
+
+use ::log::{LevelFilter, Log, Metadata, Record, SetLoggerError};
+
+use crate::raw;
+
+/// Define a Zephyr log source for the current module, and the modules nested within it.  This is
+/// the analogue of `LOG_MODULE_REGISTER`, and like it, is used once, at the top level of a module.
+/// The level is a constant [`LevelFilter`](::log::LevelFilter), and defaults to
+/// `CONFIG_RUST_LOG_LEVEL`.
+///
+/// ```ignore
+/// zephyr::log_module_register!(log::LevelFilter::Debug);
+/// ```
+#[macro_export]
+macro_rules! log_module_register {
+    () => {
+        $crate::log_module_register!($crate::log::default_level());
+    };
+    ($level:expr) => {
+        // These are placed in the same iterable sections, with the same names, that
+        // `LOG_MODULE_REGISTER` would use.
+        #[used]
+        #[link_section = ::core::concat!(
+            "._log_const.static.log_const_", ::core::module_path!(), "_"
+        )]
+        static __ZEPHYR_LOG_SOURCE: $crate::log::LogSource =
+            $crate::log::LogSource::new(::core::concat!(::core::module_path!(), "\0"), $level);
+        $crate::__log_dynamic_data!();
+    };
+}
+
+/// With runtime filtering, each source also has its filter settings in a separate section.
+#[cfg(CONFIG_LOG_RUNTIME_FILTERING)]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __log_dynamic_data {
+    () => {
+        #[used]
+        #[link_section = ::core::concat!(
+            "._log_dynamic.static.log_dynamic_", ::core::module_path!(), "_"
+        )]
+        static __ZEPHYR_LOG_DYNAMIC: $crate::log::LogDynamic = $crate::log::LogDynamic::new();
+    };
+}
+
+#[cfg(not(CONFIG_LOG_RUNTIME_FILTERING))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __log_dynamic_data {
+    () => {};
+}
+
+/// A Zephyr log source, matching `struct log_source_const_data`.  These are defined by
+/// [`log_module_register!`](crate::log_module_register).
+#[repr(C)]
+pub struct LogSource {
+    name: *const c_char,
+    level: u8,
+    #[cfg(CONFIG_NIOS2)]
+    _unused: u32,
+}
+
+// The source is never modified, and the name is a static string.
+unsafe impl Sync for LogSource {}
+
+impl LogSource {
+    /// A source with the given NUL terminated name, and level.
+    pub const fn new(name: &'static str, level: LevelFilter) -> LogSource {
+        assert!(name.as_bytes()[name.len() - 1] == 0, "log source name must be NUL terminated");
+        LogSource {
+            name: name.as_ptr() as *const c_char,
+            level: zephyr_level(level),
+            #[cfg(CONFIG_NIOS2)]
+            _unused: 0,
+        }
+    }
+
+    fn name(&self) -> &CStr {
+        unsafe { CStr::from_ptr(self.name) }
+    }
+}
+
+/// The runtime filter settings of a log source, matching `struct log_source_dynamic_data`.  These
+/// are only written by Zephyr.
+#[cfg(CONFIG_LOG_RUNTIME_FILTERING)]
+#[repr(C)]
+pub struct LogDynamic {
+    _filters: UnsafeCell<u32>,
+    #[cfg(CONFIG_NIOS2)]
+    _dummy: [u32; 2],
+    #[cfg(CONFIG_64BIT)]
+    _dummy_64: u32,
+}
+
+#[cfg(CONFIG_LOG_RUNTIME_FILTERING)]
+unsafe impl Sync for LogDynamic {}
+
+#[cfg(CONFIG_LOG_RUNTIME_FILTERING)]
+impl LogDynamic {
+    /// The filters start out clear, and are set up by Zephyr when the backends are enabled.
+    pub const fn new() -> LogDynamic {
+        unsafe { mem::zeroed() }
+    }
+}
+
+#[cfg(CONFIG_LOG_RUNTIME_FILTERING)]
+impl Default for LogDynamic {
+    fn default() -> Self {
+        LogDynamic::new()
+    }
+}
+
+// The sources are in the same table as the ones defined in C, so they must be the same size.
+const _: () = {
+    assert!(mem::size_of::<LogSource>() == raw::ZR_LOG_CONST_SIZE);
+    #[cfg(CONFIG_LOG_RUNTIME_FILTERING)]
+    assert!(mem::size_of::<LogDynamic>() == raw::ZR_LOG_DYNAMIC_SIZE);
+};
+
+/// The longest message that will be sent to Zephyr.  Longer messages are truncated.
+const MSG_SIZE: usize = 128;
+
+static LOGGER: ZephyrLogger = ZephyrLogger;
+
+extern "C" {
+    /// The bounds of the table of log sources, from C and Rust.
+    static _log_const_list_start: [LogSource; 0];
+    static _log_const_list_end: [LogSource; 0];
+
+    fn rust_log_default_source() -> *const LogSource;
+    fn rust_log_message(source: *const LogSource, level: u32, msg: *const c_char);
+}
+
+/// Install the Zephyr logger as the logger used by the `log` crate.
+///
+/// This uses the `_racy` variants in the `log` crate, as not all targets have the atomics needed
+/// by `log::set_logger`.  Calling this concurrently with other crates calling `log::set_logger` is
+/// not safe.
+pub fn set_logger() -> Result<(), SetLoggerError> {
+    unsafe {
+        ::log::set_logger_racy(&LOGGER)?;
+        ::log::set_max_level_racy(max_level());
+    }
+    Ok(())
+}
+
+/// The level of the `rust` source, from `CONFIG_RUST_LOG_LEVEL`, which is the default for
+/// [`log_module_register!`](crate::log_module_register).
+pub const fn default_level() -> LevelFilter {
+    level_filter(crate::kconfig::CONFIG_RUST_LOG_LEVEL as u8)
+}
+
+/// The `log` level for a Zephyr level.  Debug enables trace as well.
+const fn level_filter(level: u8) -> LevelFilter {
+    match level {
+        0 => LevelFilter::Off,
+        1 => LevelFilter::Error,
+        2 => LevelFilter::Warn,
+        3 => LevelFilter::Info,
+        _ => LevelFilter::Trace,
+    }
+}
+
+/// The Zephyr level for a `log` level.  Trace and debug are both debug.
+const fn zephyr_level(level: LevelFilter) -> u8 {
+    match level {
+        LevelFilter::Off => 0,
+        LevelFilter::Error => 1,
+        LevelFilter::Warn => 2,
+        LevelFilter::Info => 3,
+        LevelFilter::Debug | LevelFilter::Trace => 4,
+    }
+}
+
+/// All of the log sources, including those defined in C.
+fn sources() -> &'static [LogSource] {
+    unsafe {
+        let start = _log_const_list_start.as_ptr();
+        let end = _log_const_list_end.as_ptr();
+        slice::from_raw_parts(start, end.offset_from(start) as usize)
+    }
+}
+
+/// The `rust` source, if it is enabled.
+fn default_source() -> Option<&'static LogSource> {
+    unsafe { rust_log_default_source().as_ref() }
+}
+
+/// The most verbose level that any source can log at.  This lets the `log` macros skip messages
+/// that would never be printed.
+fn max_level() -> LevelFilter {
+    level_filter(sources().iter().map(|source| source.level).max().unwrap_or(0))
+}
+
+/// The source registered for a module path, or one of its parents.  The most specific wins.
+fn module_source(path: &str) -> Option<&'static LogSource> {
+    sources()
+        .iter()
+        .filter(|source| {
+            path.as_bytes()
+                .strip_prefix(source.name().to_bytes())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with(b"::"))
+        })
+        .max_by_key(|source| source.name().to_bytes().len())
+}
+
+/// The logger.
+struct ZephyrLogger;
+
+impl Log for ZephyrLogger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        let level = zephyr_level(metadata.level().to_level_filter());
+        module_source(metadata.target())
+            .or_else(default_source)
+            .is_some_and(|source| level <= source.level)
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        let path = record.module_path().unwrap_or(record.target());
+        let (source, named) = match module_source(path) {
+            Some(source) => (source, true),
+            None => match default_source() {
+                Some(source) => (source, false),
+                None => return,
+            },
+        };
+
+        let level = zephyr_level(record.level().to_level_filter());
+        if level > source.level {
+            return;
+        }
+
+        let mut msg = Message { len: 0, buf: [0; MSG_SIZE + 1] };
+        // Messages from the `rust` source say which module they are from.
+        let _ = if named {
+            write!(msg, "{}", record.args())
+        } else {
+            write!(msg, "{}: {}", path, record.args())
+        };
+        msg.buf[msg.len] = 0;
+        unsafe { rust_log_message(source, level as u32, msg.buf.as_ptr() as *const c_char) };
+    }
+
+    fn flush(&self) {}
+}
+
+/// A message being formatted.  Text past the end of the buffer is dropped, leaving room for the
+/// terminating NUL.
+struct Message {
+    len: usize,
+    buf: [u8; MSG_SIZE + 1],
+}
+
+impl Write for Message {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let room = MSG_SIZE - self.len;
+        let mut take = s.len().min(room);
+        // Only split on a character boundary.
+        while !s.is_char_boundary(take) {
+            take -= 1;
+        }
+        self.buf[self.len..self.len + take].copy_from_slice(&s.as_bytes()[..take]);
+        self.len += take;
+        Ok(())
+    }
+}
@@ -0,0 +1,73 @@
+// Copyright (c) 2024 Linaro LTD
+// SPDX-License-Identifier: Apache-2.0
+
+//! Lazily initialized kernel objects.
+//!
+//! Most Zephyr kernel objects need to be initialized with a call such as `k_mutex_init` before
+//! they can be used.  In C, the `K_MUTEX_DEFINE` family of macros does this statically, but the
+//! initializers refer to the address of the object itself, which can't be expressed in a Rust
+//! `const fn`.  Instead, the objects here are zero-initialized, and the init call is made the
+//! first time the object is used.
+//!
+//! Once initialized, a kernel object must not move, as the kernel keeps pointers into it.  These
+//! wrappers are therefore intended for `static` items.  The address is recorded at initialization,
+//! and use after a move will panic rather than corrupting the kernel's data structures.
+
+use core::cell::UnsafeCell;
+use core::mem;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::sys::SpinMutex;
+
+/// Serializes the initialization of all objects.  Initialization only happens once per object, so
+/// there is little to be gained from finer grained locking.
+static INIT_LOCK: SpinMutex<()> = SpinMutex::new(());
+
+/// A kernel object of type `T` that is initialized on first use.
+#[repr(C)]
+pub(crate) struct ZephyrObject<T> {
+    // This must be first, so that a pointer to the raw object can be converted back into a pointer
+    // to the containing wrapper.
+    value: UnsafeCell<T>,
+    /// Zero before initialization, the address of `value` after.
+    init: AtomicUsize,
+}
+
+impl<T> ZephyrObject<T> {
+    /// Construct a new, uninitialized object.
+    pub const fn new() -> ZephyrObject<T> {
+        ZephyrObject {
+            value: UnsafeCell::new(unsafe { mem::zeroed() }),
+            init: AtomicUsize::new(0),
+        }
+    }
+
+    /// Get the pointer to the raw object, calling `init` on it first if this is the first use.
+    ///
+    /// The init function is called with a spinlock held, and so must not block.  All of the
+    /// kernel `*_init` calls meet this.
+    pub fn get(&self, init: impl FnOnce(*mut T)) -> *mut T {
+        let ptr = self.value.get();
+        let addr = ptr as usize;
+
+        let state = self.init.load(Ordering::Acquire);
+        if state == addr {
+            return ptr;
+        }
+        if state != 0 {
+            panic!("Zephyr kernel object moved after initialization");
+        }
+
+        let _guard = INIT_LOCK.lock();
+        // Another thread may have initialized the object while we waited for the lock.
+        if self.init.load(Ordering::Acquire) == 0 {
+            init(ptr);
+            self.init.store(addr, Ordering::Release);
+        }
+        ptr
+    }
+}
+
+// The kernel objects themselves provide the synchronization.
+unsafe impl<T> Sync for ZephyrObject<T> {}
+unsafe impl<T> Send for ZephyrObject<T> {}
@@ -0,0 +1,23 @@
+// Copyright (c) 2024 Linaro LTD
+// SPDX-License-Identifier: Apache-2.0
+
+//! Synchronization primitives.
+//!
+//! These are built on the Zephyr kernel objects of the same name, and loosely follow the API of
+//! `std::sync`.  They are zero-initialized, and set up on first use, which allows them to be used
+//! in `static` items.  Because the kernel keeps pointers into these objects, they must not be moved
+//! once they have been used.
+//!
+//! All of the timeouts taken by these types accept anything that is `Into<Timeout>`, which
+//! includes [`Duration`](crate::time::Duration), [`Forever`](crate::time::Forever), and
+//! [`NoWait`](crate::time::NoWait).
+
+pub mod channel;
+
+mod condvar;
+mod mutex;
+mod semaphore;
+
+pub use condvar::{Condvar, WaitTimeoutResult};
+pub use mutex::{Mutex, MutexError, MutexGuard};
+pub use semaphore::Semaphore;
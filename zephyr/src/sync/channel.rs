@@ -0,0 +1,234 @@
+// Copyright (c) 2024 Linaro LTD
+// SPDX-License-Identifier: Apache-2.0
+
+//! Typed message passing built on `k_msgq`.
+//!
+//! A [`Channel`] holds the queue and its ring buffer, and is intended to be a `static` item.  The
+//! sending and receiving sides are obtained with [`Channel::split`].  Messages are copied into and
+//! out of the queue, so the message type must be `Copy`.  Both endpoints can be cloned, and shared
+//! between any number of threads.
+//!
+//! ```ignore
+//! static EVENTS: Channel<u32, 8> = Channel::new();
+//!
+//! let (tx, rx) = EVENTS.split();
+//! tx.send(42)?;
+//! assert_eq!(rx.recv()?, 42);
+//! ```
+
+use core::cell::UnsafeCell;
+use core::ffi::{c_char, c_void};
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem::{self, MaybeUninit};
+
+use crate::object::ZephyrObject;
+use crate::raw::{self, k_msgq};
+use crate::time::{Forever, NoWait, Timeout};
+
+/// A message queue holding up to `N` messages of type `T`.
+pub struct Channel<T: Copy + Send, const N: usize> {
+    raw: ZephyrObject<k_msgq>,
+    buffer: UnsafeCell<[MaybeUninit<T>; N]>,
+}
+
+// The buffer is only accessed by the kernel, under the queue's lock.
+unsafe impl<T: Copy + Send, const N: usize> Sync for Channel<T, N> {}
+
+impl<T: Copy + Send, const N: usize> Channel<T, N> {
+    /// Create a new, empty, channel.
+    pub const fn new() -> Channel<T, N> {
+        assert!(mem::size_of::<T>() > 0, "channel messages must not be zero sized");
+        assert!(N > 0 && N <= u32::MAX as usize, "invalid channel capacity");
+        Channel {
+            raw: ZephyrObject::new(),
+            buffer: UnsafeCell::new([const { MaybeUninit::uninit() }; N]),
+        }
+    }
+
+    fn raw(&self) -> *mut k_msgq {
+        self.raw.get(|q| unsafe {
+            raw::k_msgq_init(
+                q,
+                self.buffer.get() as *mut c_char,
+                mem::size_of::<T>(),
+                N as u32,
+            );
+        })
+    }
+
+    /// Get the sending and receiving endpoints of this channel.
+    pub fn split(&'static self) -> (Sender<T>, Receiver<T>) {
+        let queue = self.raw();
+        (
+            Sender { queue, _phantom: PhantomData },
+            Receiver { queue, _phantom: PhantomData },
+        )
+    }
+}
+
+impl<T: Copy + Send, const N: usize> Default for Channel<T, N> {
+    fn default() -> Self {
+        Channel::new()
+    }
+}
+
+/// The error returned when a message could not be sent.  The message is given back.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SendError<T> {
+    /// The queue was full, and the send was not willing to wait, or the queue was purged while
+    /// waiting.
+    Full(T),
+    /// The timeout expired before there was room in the queue.
+    TimedOut(T),
+}
+
+impl<T> SendError<T> {
+    /// Recover the message that could not be sent.
+    pub fn into_inner(self) -> T {
+        match self {
+            SendError::Full(msg) | SendError::TimedOut(msg) => msg,
+        }
+    }
+}
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SendError::Full(_) => write!(f, "channel is full"),
+            SendError::TimedOut(_) => write!(f, "timeout sending on channel"),
+        }
+    }
+}
+
+/// The error returned when a message could not be received.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecvError {
+    /// The queue was empty, and the receive was not willing to wait.
+    Empty,
+    /// The timeout expired before a message arrived.
+    TimedOut,
+}
+
+impl fmt::Display for RecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecvError::Empty => write!(f, "channel is empty"),
+            RecvError::TimedOut => write!(f, "timeout receiving on channel"),
+        }
+    }
+}
+
+/// The sending side of a [`Channel`].
+pub struct Sender<T> {
+    queue: *mut k_msgq,
+    _phantom: PhantomData<T>,
+}
+
+unsafe impl<T: Send> Send for Sender<T> {}
+unsafe impl<T: Send> Sync for Sender<T> {}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        Sender { queue: self.queue, _phantom: PhantomData }
+    }
+}
+
+impl<T: Copy + Send> Sender<T> {
+    /// Send a message, waiting as long as necessary for room in the queue.
+    pub fn send(&self, msg: T) -> Result<(), SendError<T>> {
+        self.send_timeout(msg, Forever)
+    }
+
+    /// Send a message, only if there is room in the queue.  This can be called from interrupt
+    /// context.
+    pub fn try_send(&self, msg: T) -> Result<(), SendError<T>> {
+        self.send_timeout(msg, NoWait)
+    }
+
+    /// Send a message, waiting no longer than the given timeout for room in the queue.
+    pub fn send_timeout<TO>(&self, msg: T, timeout: TO) -> Result<(), SendError<T>>
+        where TO: Into<Timeout>,
+    {
+        let timeout: Timeout = timeout.into();
+        let code = unsafe {
+            raw::k_msgq_put(self.queue, &msg as *const T as *const c_void, timeout.0)
+        };
+        match code {
+            0 => Ok(()),
+            code if code.unsigned_abs() == raw::EAGAIN => Err(SendError::TimedOut(msg)),
+            _ => Err(SendError::Full(msg)),
+        }
+    }
+
+    /// The number of messages currently in the queue.
+    pub fn num_used(&self) -> u32 {
+        unsafe { raw::k_msgq_num_used_get(self.queue) }
+    }
+}
+
+/// The receiving side of a [`Channel`].
+pub struct Receiver<T> {
+    queue: *mut k_msgq,
+    _phantom: PhantomData<T>,
+}
+
+unsafe impl<T: Send> Send for Receiver<T> {}
+unsafe impl<T: Send> Sync for Receiver<T> {}
+
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Self {
+        Receiver { queue: self.queue, _phantom: PhantomData }
+    }
+}
+
+impl<T: Copy + Send> Receiver<T> {
+    /// Receive a message, waiting as long as necessary for one to arrive.
+    pub fn recv(&self) -> Result<T, RecvError> {
+        self.recv_timeout(Forever)
+    }
+
+    /// Receive a message, only if one is already available.  This can be called from interrupt
+    /// context.
+    pub fn try_recv(&self) -> Result<T, RecvError> {
+        self.recv_timeout(NoWait)
+    }
+
+    /// Receive a message, waiting no longer than the given timeout for one to arrive.
+    pub fn recv_timeout<TO>(&self, timeout: TO) -> Result<T, RecvError>
+        where TO: Into<Timeout>,
+    {
+        let timeout: Timeout = timeout.into();
+        let mut msg = MaybeUninit::<T>::uninit();
+        let code = unsafe {
+            raw::k_msgq_get(self.queue, msg.as_mut_ptr() as *mut c_void, timeout.0)
+        };
+        match code {
+            0 => Ok(unsafe { msg.assume_init() }),
+            code if code.unsigned_abs() == raw::EAGAIN => Err(RecvError::TimedOut),
+            _ => Err(RecvError::Empty),
+        }
+    }
+
+    /// Return a copy of the message at the head of the queue, without removing it.
+    pub fn peek(&self) -> Option<T> {
+        let mut msg = MaybeUninit::<T>::uninit();
+        let code = unsafe { raw::k_msgq_peek(self.queue, msg.as_mut_ptr() as *mut c_void) };
+        if code == 0 {
+            Some(unsafe { msg.assume_init() })
+        } else {
+            None
+        }
+    }
+
+    /// Discard all messages in the queue.  Any threads waiting to send are woken, and their sends
+    /// fail with [`SendError::Full`].
+    pub fn purge(&self) {
+        unsafe { raw::k_msgq_purge(self.queue) }
+    }
+
+    /// The number of messages currently in the queue.
+    pub fn num_used(&self) -> u32 {
+        unsafe { raw::k_msgq_num_used_get(self.queue) }
+    }
+}
@@ -0,0 +1,106 @@
+// Copyright (c) 2024 Linaro LTD
+// SPDX-License-Identifier: Apache-2.0
+
+//! A condition variable built on `k_condvar`.
+
+use core::fmt;
+
+use crate::error::{Error, Result};
+use crate::object::ZephyrObject;
+use crate::raw::{self, k_condvar};
+use crate::time::{Forever, Timeout};
+
+use super::MutexGuard;
+
+/// A condition variable, used along with a [`Mutex`](super::Mutex) to wait for some condition to
+/// become true.
+///
+/// As with any condition variable, wakeups can be spurious, so the condition should be rechecked
+/// in a loop.
+///
+/// ```ignore
+/// static READY: Mutex<bool> = Mutex::new(false);
+/// static CHANGED: Condvar = Condvar::new();
+///
+/// let mut ready = READY.lock()?;
+/// while !*ready {
+///     ready = CHANGED.wait(ready)?;
+/// }
+/// ```
+pub struct Condvar {
+    raw: ZephyrObject<k_condvar>,
+}
+
+/// Indicates whether [`Condvar::wait_timeout`] returned because the timeout expired.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WaitTimeoutResult(bool);
+
+impl WaitTimeoutResult {
+    /// Returns true if the wait ended because the timeout expired.
+    pub fn timed_out(&self) -> bool {
+        self.0
+    }
+}
+
+impl Condvar {
+    /// Create a new condition variable.
+    pub const fn new() -> Condvar {
+        Condvar {
+            raw: ZephyrObject::new(),
+        }
+    }
+
+    fn raw(&self) -> *mut k_condvar {
+        self.raw.get(|c| unsafe {
+            raw::k_condvar_init(c);
+        })
+    }
+
+    /// Release the mutex held by `guard`, and wait for a notification.  The mutex is locked again
+    /// before this returns.
+    pub fn wait<'a, T: ?Sized>(&self, guard: MutexGuard<'a, T>) -> Result<MutexGuard<'a, T>> {
+        self.wait_timeout(guard, Forever).map(|(guard, _)| guard)
+    }
+
+    /// Like [`wait`](Self::wait), but gives up after the given timeout.  The mutex is locked again
+    /// in either case.
+    pub fn wait_timeout<'a, T, TO>(
+        &self,
+        guard: MutexGuard<'a, T>,
+        timeout: TO,
+    ) -> Result<(MutexGuard<'a, T>, WaitTimeoutResult)>
+        where T: ?Sized,
+              TO: Into<Timeout>,
+    {
+        let timeout: Timeout = timeout.into();
+        let mutex = guard.mutex();
+        let code = unsafe { raw::k_condvar_wait(self.raw(), mutex.raw(), timeout.0) };
+        match code {
+            0 => Ok((guard, WaitTimeoutResult(false))),
+            code if code.unsigned_abs() == raw::EAGAIN => Ok((guard, WaitTimeoutResult(true))),
+            code => Err(Error(code.unsigned_abs())),
+        }
+    }
+
+    /// Wake up one thread waiting on this condition variable.
+    pub fn notify_one(&self) {
+        unsafe { raw::k_condvar_signal(self.raw()) };
+    }
+
+    /// Wake up all threads waiting on this condition variable.
+    pub fn notify_all(&self) {
+        unsafe { raw::k_condvar_broadcast(self.raw()) };
+    }
+}
+
+impl Default for Condvar {
+    fn default() -> Self {
+        Condvar::new()
+    }
+}
+
+impl fmt::Debug for Condvar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Condvar {{ .. }}")
+    }
+}
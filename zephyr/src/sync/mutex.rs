@@ -0,0 +1,222 @@
+// Copyright (c) 2024 Linaro LTD
+// SPDX-License-Identifier: Apache-2.0
+
+//! A mutex built on `k_mutex`.
+
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem;
+use core::ops::{Deref, DerefMut};
+
+use crate::error::Error;
+use crate::object::ZephyrObject;
+use crate::raw::{self, k_mutex};
+use crate::time::{Forever, NoWait, Timeout};
+
+/// Errors that can be returned by the mutex operations.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MutexError {
+    /// The mutex was locked, and the call was not willing to wait (`EBUSY`).
+    WouldBlock,
+    /// The timeout expired before the mutex could be locked (`EAGAIN`).
+    TimedOut,
+    /// The current thread already holds this mutex.  Zephyr mutexes are recursive, but this would
+    /// give out two mutable references to the data.
+    Recursive,
+    /// The current thread is not the owner of the mutex (`EPERM`).
+    NotOwner,
+    /// The mutex is not locked (`EINVAL`).
+    NotLocked,
+    /// Any other error from Zephyr.
+    Other(Error),
+}
+
+impl MutexError {
+    fn from_lock(code: core::ffi::c_int) -> MutexError {
+        match code.unsigned_abs() {
+            raw::EBUSY => MutexError::WouldBlock,
+            raw::EAGAIN => MutexError::TimedOut,
+            errno => MutexError::Other(Error(errno)),
+        }
+    }
+
+    fn from_unlock(code: core::ffi::c_int) -> MutexError {
+        match code.unsigned_abs() {
+            raw::EPERM => MutexError::NotOwner,
+            raw::EINVAL => MutexError::NotLocked,
+            errno => MutexError::Other(Error(errno)),
+        }
+    }
+}
+
+impl fmt::Display for MutexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MutexError::WouldBlock => write!(f, "mutex is locked"),
+            MutexError::TimedOut => write!(f, "timeout waiting for mutex"),
+            MutexError::Recursive => write!(f, "mutex locked recursively"),
+            MutexError::NotOwner => write!(f, "mutex not owned by current thread"),
+            MutexError::NotLocked => write!(f, "mutex not locked"),
+            MutexError::Other(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<MutexError> for Error {
+    fn from(value: MutexError) -> Error {
+        match value {
+            MutexError::WouldBlock => Error(raw::EBUSY),
+            MutexError::TimedOut => Error(raw::EAGAIN),
+            MutexError::Recursive => Error(raw::EDEADLK),
+            MutexError::NotOwner => Error(raw::EPERM),
+            MutexError::NotLocked => Error(raw::EINVAL),
+            MutexError::Other(e) => e,
+        }
+    }
+}
+
+/// A mutual exclusion primitive protecting data of type `T`.
+///
+/// The data can only be accessed through the [`MutexGuard`] returned by the lock calls, which
+/// unlocks the mutex when it is dropped.  As with `k_mutex`, the mutex has an owning thread, and
+/// supports priority inheritance.
+///
+/// ```ignore
+/// static COUNT: Mutex<u32> = Mutex::new(0);
+///
+/// *COUNT.lock().unwrap() += 1;
+/// ```
+pub struct Mutex<T: ?Sized> {
+    raw: ZephyrObject<k_mutex>,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: ?Sized + Send> Send for Mutex<T> {}
+unsafe impl<T: ?Sized + Send> Sync for Mutex<T> {}
+
+/// An RAII guard for a locked [`Mutex`].  The data is accessible through `Deref` and `DerefMut`,
+/// and the mutex is unlocked when this is dropped.
+pub struct MutexGuard<'a, T: ?Sized + 'a> {
+    mutex: &'a Mutex<T>,
+    // Zephyr mutexes must be unlocked by the thread that locked them.
+    _not_send: PhantomData<*const ()>,
+}
+
+unsafe impl<T: ?Sized + Sync> Sync for MutexGuard<'_, T> {}
+
+impl<T> Mutex<T> {
+    /// Create a new mutex, in the unlocked state.
+    pub const fn new(t: T) -> Mutex<T> {
+        Mutex {
+            raw: ZephyrObject::new(),
+            data: UnsafeCell::new(t),
+        }
+    }
+
+    /// Consume the mutex, returning the underlying data.
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+impl<T: ?Sized> Mutex<T> {
+    /// Get the raw `k_mutex`, initializing it if this is the first use.
+    pub(crate) fn raw(&self) -> *mut k_mutex {
+        self.raw.get(|m| unsafe {
+            raw::k_mutex_init(m);
+        })
+    }
+
+    /// Lock the mutex, waiting as long as necessary.
+    pub fn lock(&self) -> Result<MutexGuard<'_, T>, MutexError> {
+        self.try_lock_for(Forever)
+    }
+
+    /// Attempt to lock the mutex without waiting.
+    pub fn try_lock(&self) -> Result<MutexGuard<'_, T>, MutexError> {
+        self.try_lock_for(NoWait)
+    }
+
+    /// Attempt to lock the mutex, waiting no longer than the given timeout.
+    pub fn try_lock_for<TO>(&self, timeout: TO) -> Result<MutexGuard<'_, T>, MutexError>
+        where TO: Into<Timeout>,
+    {
+        let timeout: Timeout = timeout.into();
+        let raw = self.raw();
+        let code = unsafe { raw::k_mutex_lock(raw, timeout.0) };
+        if code != 0 {
+            return Err(MutexError::from_lock(code));
+        }
+
+        // A recursive lock succeeds in Zephyr, but would alias the guard that is already out.
+        if unsafe { (*raw).lock_count } > 1 {
+            unsafe { raw::k_mutex_unlock(raw) };
+            return Err(MutexError::Recursive);
+        }
+
+        Ok(MutexGuard {
+            mutex: self,
+            _not_send: PhantomData,
+        })
+    }
+
+    /// Get a mutable reference to the data.  Since this borrows the mutex mutably, no locking is
+    /// needed.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+}
+
+impl<T: Default> Default for Mutex<T> {
+    fn default() -> Self {
+        Mutex::new(T::default())
+    }
+}
+
+impl<T: ?Sized> fmt::Debug for Mutex<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Mutex {{ .. }}")
+    }
+}
+
+impl<'a, T: ?Sized> MutexGuard<'a, T> {
+    /// Unlock the mutex, reporting any error from Zephyr.  Dropping the guard also unlocks the
+    /// mutex, but has no way to report errors.
+    pub fn unlock(guard: MutexGuard<'a, T>) -> Result<(), MutexError> {
+        let raw = guard.mutex.raw();
+        mem::forget(guard);
+        let code = unsafe { raw::k_mutex_unlock(raw) };
+        if code == 0 {
+            Ok(())
+        } else {
+            Err(MutexError::from_unlock(code))
+        }
+    }
+
+    /// The mutex this guard is for.
+    pub(crate) fn mutex(&self) -> &'a Mutex<T> {
+        self.mutex
+    }
+}
+
+impl<T: ?Sized> Deref for MutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for MutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.mutex.data.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for MutexGuard<'_, T> {
+    fn drop(&mut self) {
+        let code = unsafe { raw::k_mutex_unlock(self.mutex.raw()) };
+        debug_assert_eq!(code, 0, "k_mutex_unlock failed");
+    }
+}
@@ -0,0 +1,79 @@
+// Copyright (c) 2024 Linaro LTD
+// SPDX-License-Identifier: Apache-2.0
+
+//! A counting semaphore built on `k_sem`.
+
+use core::ffi::c_uint;
+use core::fmt;
+
+use crate::error::{to_result_void, Result};
+use crate::object::ZephyrObject;
+use crate::raw::{self, k_sem};
+use crate::time::Timeout;
+
+/// A counting semaphore.
+///
+/// The count starts at `initial`, and is never allowed to exceed `limit`.  Taking the semaphore
+/// decrements the count, waiting if it is zero, and giving increments it.
+///
+/// ```ignore
+/// static READY: Semaphore = Semaphore::new(0, 1);
+///
+/// READY.give();
+/// READY.take(Duration::millis(100))?;
+/// ```
+pub struct Semaphore {
+    raw: ZephyrObject<k_sem>,
+    initial: c_uint,
+    limit: c_uint,
+}
+
+impl Semaphore {
+    /// Create a new semaphore, with the given initial count and limit.  The limit must not be
+    /// zero, and the initial count must not be larger than the limit.
+    pub const fn new(initial: u32, limit: u32) -> Semaphore {
+        assert!(limit != 0 && initial <= limit, "invalid semaphore count or limit");
+        Semaphore {
+            raw: ZephyrObject::new(),
+            initial,
+            limit,
+        }
+    }
+
+    fn raw(&self) -> *mut k_sem {
+        self.raw.get(|s| unsafe {
+            raw::k_sem_init(s, self.initial, self.limit);
+        })
+    }
+
+    /// Take the semaphore, waiting up to the given timeout.  Returns `EBUSY` if the semaphore is
+    /// not available and the timeout is `NoWait`, or `EAGAIN` if the timeout expired.
+    pub fn take<T>(&self, timeout: T) -> Result<()>
+        where T: Into<Timeout>,
+    {
+        let timeout: Timeout = timeout.into();
+        to_result_void(unsafe { raw::k_sem_take(self.raw(), timeout.0) })
+    }
+
+    /// Give the semaphore, incrementing the count unless it is already at the limit.  This can be
+    /// called from interrupt context.
+    pub fn give(&self) {
+        unsafe { raw::k_sem_give(self.raw()) }
+    }
+
+    /// The current count of the semaphore.
+    pub fn count(&self) -> u32 {
+        unsafe { raw::k_sem_count_get(self.raw()) }
+    }
+
+    /// Reset the count to zero.  Any threads waiting on the semaphore are woken with `EAGAIN`.
+    pub fn reset(&self) {
+        unsafe { raw::k_sem_reset(self.raw()) }
+    }
+}
+
+impl fmt::Debug for Semaphore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Semaphore {{ limit: {} }}", self.limit)
+    }
+}
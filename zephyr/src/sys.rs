@@ -9,10 +9,83 @@
 //! This module `zephyr::sys` contains thin wrappers to these C bindings, that can be used without
 //! unsafe, but as unchanged as possible.
 
-use zephyr_sys::k_timeout_t;
+use core::cell::UnsafeCell;
+use core::marker::PhantomData;
+use core::mem;
+use core::ops::{Deref, DerefMut};
+
+use zephyr_sys::{k_spin_lock, k_spin_unlock, k_spinlock, k_spinlock_key_t, k_timeout_t};
 
 // These two constants are not able to be captured by bindgen.  It is unlikely that these values
 // would change in the Zephyr headers, but there will be an explicit test to make sure they are
 // correct.
 pub const K_FOREVER: k_timeout_t = k_timeout_t { ticks: -1 };
 pub const K_NO_WAIT: k_timeout_t = k_timeout_t { ticks: 0 };
+
+/// A spinlock protected value.
+///
+/// This wraps `k_spinlock`.  While the lock is held, interrupts are masked on the current CPU, and
+/// on SMP systems, other CPUs will spin waiting for the lock.  As such, it should only be held for
+/// very short sections of code, but can be used from any context, including interrupt handlers.
+///
+/// Unlike most of Zephyr's kernel objects, a `k_spinlock` is valid when zero-initialized, so this
+/// can be used directly in `static` items.
+pub struct SpinMutex<T> {
+    lock: UnsafeCell<k_spinlock>,
+    data: UnsafeCell<T>,
+}
+
+// The lock ensures only a single context has access to the data at a time.
+unsafe impl<T: Send> Send for SpinMutex<T> {}
+unsafe impl<T: Send> Sync for SpinMutex<T> {}
+
+/// The guard returned by [`SpinMutex::lock`].  The lock is released when this is dropped.
+pub struct SpinMutexGuard<'a, T> {
+    mutex: &'a SpinMutex<T>,
+    // The key is only taken out by drop.
+    key: Option<k_spinlock_key_t>,
+    // The guard must be released on the same CPU, and in the same context, it was taken on.
+    _not_send: PhantomData<*const ()>,
+}
+
+impl<T> SpinMutex<T> {
+    /// Create a new SpinMutex holding the given value.
+    pub const fn new(data: T) -> SpinMutex<T> {
+        SpinMutex {
+            lock: UnsafeCell::new(unsafe { mem::zeroed() }),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    /// Acquire the lock, spinning if necessary.
+    pub fn lock(&self) -> SpinMutexGuard<'_, T> {
+        let key = unsafe { k_spin_lock(self.lock.get()) };
+        SpinMutexGuard {
+            mutex: self,
+            key: Some(key),
+            _not_send: PhantomData,
+        }
+    }
+}
+
+impl<T> Deref for SpinMutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<T> DerefMut for SpinMutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.mutex.data.get() }
+    }
+}
+
+impl<T> Drop for SpinMutexGuard<'_, T> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            unsafe { k_spin_unlock(self.mutex.lock.get(), key) };
+        }
+    }
+}
@@ -0,0 +1,402 @@
+// Copyright (c) 2024 Linaro LTD
+// SPDX-License-Identifier: Apache-2.0
+
+//! Zephyr threads.
+//!
+//! Threads in Zephyr need two pieces of memory: the thread control block (`struct k_thread`), and
+//! a stack.  Both of these need to live as long as the thread runs, and the stack has alignment and
+//! size requirements that depend on the architecture and on whether memory protection is enabled.
+//! Without an allocator, the simplest way to meet this is to have both be `static` items.
+//!
+//! A stack is declared with the [`thread_stack!`](crate::thread_stack) macro, which computes the
+//! same size and alignment that `K_THREAD_STACK_DEFINE` would in C.  The control block is a
+//! [`Thread`].  A [`Builder`] is then used to start the thread.
+//!
+//! ```ignore
+//! use zephyr::thread::{Builder, Thread};
+//!
+//! zephyr::thread_stack!(WORKER_STACK, 2048);
+//! static WORKER: Thread = Thread::new();
+//!
+//! fn worker() {
+//!     // ...
+//! }
+//!
+//! let mut handle = Builder::new()
+//!     .priority(5)
+//!     .name(c"worker")
+//!     .spawn(&WORKER, &WORKER_STACK, worker)?;
+//! handle.join(Forever)?;
+//! ```
+//!
+//! The `Thread` and stack remain in use until the thread has been successfully joined, after which
+//! they can be used to start another thread.
+
+use core::cell::UnsafeCell;
+use core::ffi::{c_int, c_void, CStr};
+use core::mem;
+use core::ptr;
+
+use crate::error::{to_result_void, Error, Result};
+use crate::raw::{self, k_thread, k_tid_t, z_thread_stack_element};
+use crate::sys::{SpinMutex, K_FOREVER, K_NO_WAIT};
+use crate::time::Timeout;
+
+/// The alignment of a thread stack object, before any size-dependent adjustment.
+pub const STACK_ALIGN: usize = raw::ZR_STACK_ALIGN;
+
+/// The amount of each thread stack that is reserved by the kernel, and not usable by the thread.
+pub const STACK_RESERVED: usize = raw::ZR_STACK_RESERVED;
+
+/// Compute the alignment of a stack that can hold `size` bytes, matching `K_THREAD_STACK_DEFINE`.
+///
+/// When user mode is enabled on an MPU that requires power-of-two aligned regions, the stack must
+/// be aligned to its own (rounded up) size so that it can be covered by a single region.
+pub const fn stack_align(size: usize) -> usize {
+    if cfg!(all(CONFIG_USERSPACE, CONFIG_MPU_REQUIRES_POWER_OF_TWO_ALIGNMENT)) {
+        let size = (size + STACK_RESERVED).next_power_of_two();
+        if size > STACK_ALIGN { size } else { STACK_ALIGN }
+    } else {
+        STACK_ALIGN
+    }
+}
+
+/// Compute the length of the stack object that holds `size` usable bytes, matching
+/// `K_THREAD_STACK_LEN`, which rounds the size plus the reserved area up to the alignment.
+pub const fn stack_len(size: usize) -> usize {
+    (size + STACK_RESERVED).next_multiple_of(stack_align(size))
+}
+
+// The macro itself can't be used from Rust, so check against its value for a few sizes.
+const _: () = {
+    assert!(stack_len(100) == raw::ZR_STACK_LEN_100);
+    assert!(stack_len(1000) == raw::ZR_STACK_LEN_1000);
+    assert!(stack_len(4096) == raw::ZR_STACK_LEN_4096);
+};
+
+/// Declare a static thread stack, with room for `size` bytes.
+///
+/// ```ignore
+/// zephyr::thread_stack!(STACK, 1024);
+/// zephyr::thread_stack!(pub SHARED_STACK, 4096);
+/// ```
+#[macro_export]
+macro_rules! thread_stack {
+    ($vis:vis $name:ident, $size:expr) => {
+        $vis static $name: $crate::thread::ThreadStack<
+            { $crate::thread::stack_len($size) },
+            { $crate::thread::stack_align($size) },
+        > = $crate::thread::ThreadStack::new();
+    };
+}
+
+/// Types that have the alignment requested by their parameter.  This is used to give a stack an
+/// alignment computed by a const fn, which `#[repr(align)]` can't do directly.
+#[doc(hidden)]
+pub trait Alignment {
+    type Aligned;
+}
+
+/// Zero-sized marker used with [`Alignment`].
+#[doc(hidden)]
+pub struct AlignAs<const N: usize>;
+
+macro_rules! impl_alignment {
+    ($($n:literal => $name:ident),* $(,)?) => {
+        $(
+            #[doc(hidden)]
+            #[repr(align($n))]
+            pub struct $name;
+
+            impl Alignment for AlignAs<$n> {
+                type Aligned = $name;
+            }
+        )*
+    };
+}
+
+impl_alignment! {
+    1 => Align1, 2 => Align2, 4 => Align4, 8 => Align8, 16 => Align16, 32 => Align32,
+    64 => Align64, 128 => Align128, 256 => Align256, 512 => Align512, 1024 => Align1024,
+    2048 => Align2048, 4096 => Align4096, 8192 => Align8192, 16384 => Align16384,
+    32768 => Align32768, 65536 => Align65536,
+}
+
+/// A thread stack.  These should be declared with [`thread_stack!`](crate::thread_stack), which
+/// computes the generic parameters.
+#[repr(C)]
+pub struct ThreadStack<const SIZE: usize, const ALIGN: usize>
+where
+    AlignAs<ALIGN>: Alignment,
+{
+    _align: [<AlignAs<ALIGN> as Alignment>::Aligned; 0],
+    data: UnsafeCell<[z_thread_stack_element; SIZE]>,
+    in_use: SpinMutex<bool>,
+}
+
+// The stack memory is only accessed by the thread that is using it.
+unsafe impl<const SIZE: usize, const ALIGN: usize> Sync for ThreadStack<SIZE, ALIGN>
+where
+    AlignAs<ALIGN>: Alignment,
+{}
+
+impl<const SIZE: usize, const ALIGN: usize> ThreadStack<SIZE, ALIGN>
+where
+    AlignAs<ALIGN>: Alignment,
+{
+    /// Construct a new stack.  Use [`thread_stack!`](crate::thread_stack) instead of calling this
+    /// directly, to ensure the parameters are correct.
+    pub const fn new() -> Self {
+        ThreadStack {
+            _align: [],
+            data: UnsafeCell::new(unsafe { mem::zeroed() }),
+            in_use: SpinMutex::new(false),
+        }
+    }
+
+    /// The raw pointer to the stack, suitable for `k_thread_create` and similar calls.
+    pub fn as_raw(&self) -> *mut z_thread_stack_element {
+        self.data.get() as *mut z_thread_stack_element
+    }
+
+    /// The size to give to `k_thread_create`, which matches `K_THREAD_STACK_SIZEOF`.
+    pub const fn size(&self) -> usize {
+        SIZE - STACK_RESERVED
+    }
+
+    /// Mark this stack as in use, failing if it already is.
+    pub(crate) fn claim(&'static self) -> Result<&'static SpinMutex<bool>> {
+        claim(&self.in_use)?;
+        Ok(&self.in_use)
+    }
+}
+
+impl<const SIZE: usize, const ALIGN: usize> Default for ThreadStack<SIZE, ALIGN>
+where
+    AlignAs<ALIGN>: Alignment,
+{
+    fn default() -> Self {
+        ThreadStack::new()
+    }
+}
+
+/// Claim a resource guarded by an in-use flag.
+fn claim(flag: &SpinMutex<bool>) -> Result<()> {
+    let mut in_use = flag.lock();
+    if *in_use {
+        return Err(Error(raw::EBUSY));
+    }
+    *in_use = true;
+    Ok(())
+}
+
+/// The control block for a thread.  This must be static, and will be used by a single running
+/// thread at a time.
+pub struct Thread {
+    raw: UnsafeCell<k_thread>,
+    in_use: SpinMutex<bool>,
+}
+
+// The control block itself is only manipulated by the kernel.
+unsafe impl Sync for Thread {}
+
+impl Thread {
+    /// Construct a new thread control block.  It is initialized by the kernel when the thread is
+    /// created.
+    pub const fn new() -> Thread {
+        Thread {
+            raw: UnsafeCell::new(unsafe { mem::zeroed() }),
+            in_use: SpinMutex::new(false),
+        }
+    }
+}
+
+impl Default for Thread {
+    fn default() -> Self {
+        Thread::new()
+    }
+}
+
+/// Configuration for a new thread.
+pub struct Builder {
+    priority: c_int,
+    options: u32,
+    delay: Timeout,
+    name: Option<&'static CStr>,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Builder::new()
+    }
+}
+
+impl Builder {
+    /// A builder for a thread with priority zero, no options, that starts immediately.
+    pub fn new() -> Builder {
+        Builder {
+            priority: 0,
+            options: 0,
+            delay: Timeout(K_NO_WAIT),
+            name: None,
+        }
+    }
+
+    /// Set the priority of the thread.  Negative values are cooperative priorities.
+    pub fn priority(mut self, priority: c_int) -> Builder {
+        self.priority = priority;
+        self
+    }
+
+    /// Set the thread options, such as `K_ESSENTIAL` or `K_FP_REGS`.
+    pub fn options(mut self, options: u32) -> Builder {
+        self.options = options;
+        self
+    }
+
+    /// Set the delay before the thread starts.  `Forever` creates the thread without starting it,
+    /// and it can then be started with [`JoinHandle::start`].
+    pub fn delay<T>(mut self, delay: T) -> Builder
+        where T: Into<Timeout>,
+    {
+        self.delay = delay.into();
+        self
+    }
+
+    /// Set the name of the thread.  This is ignored if `CONFIG_THREAD_NAME` is not enabled.
+    pub fn name(mut self, name: &'static CStr) -> Builder {
+        self.name = Some(name);
+        self
+    }
+
+    /// Create the thread, running `entry` on the given control block and stack.  Fails with
+    /// `EBUSY` if either is already being used by another thread.
+    pub fn spawn<const SIZE: usize, const ALIGN: usize>(
+        self,
+        thread: &'static Thread,
+        stack: &'static ThreadStack<SIZE, ALIGN>,
+        entry: fn(),
+    ) -> Result<JoinHandle>
+        where AlignAs<ALIGN>: Alignment,
+    {
+        claim(&thread.in_use)?;
+        let stack_in_use = match stack.claim() {
+            Ok(flag) => flag,
+            Err(e) => {
+                *thread.in_use.lock() = false;
+                return Err(e);
+            }
+        };
+
+        // To allow the name to be set before the thread runs, threads that should start
+        // immediately are created suspended and then started.
+        let start_now = self.delay.0.ticks == K_NO_WAIT.ticks;
+        let delay = if start_now { K_FOREVER } else { self.delay.0 };
+
+        let tid = unsafe {
+            raw::k_thread_create(
+                thread.raw.get(),
+                stack.as_raw(),
+                stack.size(),
+                Some(thread_entry),
+                entry as *mut c_void,
+                ptr::null_mut(),
+                ptr::null_mut(),
+                self.priority,
+                self.options,
+                delay,
+            )
+        };
+
+        #[cfg(CONFIG_THREAD_NAME)]
+        if let Some(name) = self.name {
+            unsafe { raw::k_thread_name_set(tid, name.as_ptr()) };
+        }
+        #[cfg(not(CONFIG_THREAD_NAME))]
+        let _ = self.name;
+
+        if start_now {
+            unsafe { raw::k_thread_start(tid) };
+        }
+
+        Ok(JoinHandle {
+            tid,
+            thread_in_use: &thread.in_use,
+            stack_in_use,
+            joined: false,
+        })
+    }
+}
+
+unsafe extern "C" fn thread_entry(p1: *mut c_void, _p2: *mut c_void, _p3: *mut c_void) {
+    let entry: fn() = mem::transmute(p1);
+    entry();
+}
+
+/// A handle to a running thread.
+///
+/// Dropping the handle detaches the thread, which will continue to run, but the `Thread` and stack
+/// it uses can then never be reused.
+pub struct JoinHandle {
+    tid: k_tid_t,
+    thread_in_use: &'static SpinMutex<bool>,
+    stack_in_use: &'static SpinMutex<bool>,
+    joined: bool,
+}
+
+// The thread id can be used from any thread.
+unsafe impl Send for JoinHandle {}
+unsafe impl Sync for JoinHandle {}
+
+impl JoinHandle {
+    /// The raw thread id.
+    pub fn tid(&self) -> k_tid_t {
+        self.tid
+    }
+
+    /// Wait for the thread to exit.  Returns `EAGAIN` if the timeout expires, or `EBUSY` if given
+    /// `NoWait` and the thread is still running.  Once this has succeeded, the `Thread` and stack
+    /// are released so they can be used to create another thread.
+    pub fn join<T>(&mut self, timeout: T) -> Result<()>
+        where T: Into<Timeout>,
+    {
+        if self.joined {
+            return Ok(());
+        }
+        let timeout: Timeout = timeout.into();
+        to_result_void(unsafe { raw::k_thread_join(self.tid, timeout.0) })?;
+        self.joined = true;
+        *self.stack_in_use.lock() = false;
+        *self.thread_in_use.lock() = false;
+        Ok(())
+    }
+
+    /// Start a thread that was created with a delay of `Forever`.  This has no effect on a thread
+    /// that has already started.
+    pub fn start(&self) {
+        if !self.joined {
+            unsafe { raw::k_thread_start(self.tid) };
+        }
+    }
+
+    /// Abort the thread.  It can still be joined afterwards to release its resources.
+    pub fn abort(&self) {
+        if !self.joined {
+            unsafe { raw::k_thread_abort(self.tid) };
+        }
+    }
+
+    /// Suspend the thread.
+    pub fn suspend(&self) {
+        if !self.joined {
+            unsafe { raw::k_thread_suspend(self.tid) };
+        }
+    }
+
+    /// Resume a thread that was suspended.
+    pub fn resume(&self) {
+        if !self.joined {
+            unsafe { raw::k_thread_resume(self.tid) };
+        }
+    }
+}
@@ -26,14 +26,14 @@
 
 use zephyr_sys::{k_timeout_t, k_ticks_t};
 
-use core::fmt::Debug;
+use core::fmt;
+use core::ops::{Add, AddAssign, Sub, SubAssign};
 
-// The system ticks, is mostly a constant, but there are some boards that use a dynamic tick
-// frequency, and thus need to read this at runtime.
-#[cfg(CONFIG_TIMER_READS_ITS_FREQUENCY_AT_RUNTIME)]
-compile_error!("Rust does not (yet) support dynamic frequency timer");
+use crate::thread::JoinHandle;
 
-// Given the above not defined, the system time base comes from a kconfig.
+// Some timers, such as the HPET used on x86, read their hardware cycle frequency at runtime.  That
+// only affects `sys_clock_hw_cycles_per_sec()`.  The tick rate, which is all that is used here, is
+// always the configured constant.
 /// The system time base.  The system clock has this many ticks per second.
 pub const SYS_FREQUENCY: u32 = crate::kconfig::CONFIG_SYS_CLOCK_TICKS_PER_SEC as u32;
 
@@ -49,10 +49,124 @@ pub type Tick = u32;
 /// interval from "now" (when the call is made).
 pub type Duration = fugit::Duration<Tick, 1, SYS_FREQUENCY>;
 
+/// The fugit type underlying `Instant`.
+type RawInstant = fugit::Instant<Tick, 1, SYS_FREQUENCY>;
+
 /// An Instant appropriate for Zephyr calls that expect a `k_timeout_t`.  The result will be an
 /// absolute time in terms of system ticks.
-#[cfg(CONFIG_TIMEOUT_64BIT)]
-pub type Instant = fugit::Instant<Tick, 1, SYS_FREQUENCY>;
+///
+/// Because an `Instant` is absolute, it can be used to wait for deadlines without accumulating
+/// drift:
+///
+/// ```ignore
+/// let mut next = Instant::now();
+/// loop {
+///     next += Duration::millis(100);
+///     sleep_until(next);
+///     // ...
+/// }
+/// ```
+///
+/// With 32-bit ticks, the tick count wraps around, which can happen after days or even hours,
+/// depending on the tick rate.  Comparisons and arithmetic take the wrapping into account, so they
+/// give correct results as long as the Instants involved are less than half the range apart.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Instant(RawInstant);
+
+impl Instant {
+    /// The current time, from `k_uptime_ticks`.
+    pub fn now() -> Instant {
+        // With 32-bit ticks, this truncates, giving the wrapping count.
+        let ticks = unsafe { crate::raw::k_uptime_ticks() };
+        Instant::from_ticks(ticks as Tick)
+    }
+
+    /// Construct an Instant from a number of ticks since the system started.
+    pub const fn from_ticks(ticks: Tick) -> Instant {
+        Instant(RawInstant::from_ticks(ticks))
+    }
+
+    /// The number of ticks from the start of the system to this Instant.
+    pub const fn ticks(&self) -> Tick {
+        self.0.ticks()
+    }
+
+    /// The time elapsed from `earlier` to this Instant, or `None` if `earlier` is later than this
+    /// Instant.
+    pub fn checked_duration_since(&self, earlier: Instant) -> Option<Duration> {
+        self.0.checked_duration_since(earlier.0)
+    }
+
+    /// The time elapsed from `earlier` to this Instant.  Returns zero if `earlier` is later than
+    /// this Instant.
+    pub fn duration_since(&self, earlier: Instant) -> Duration {
+        self.checked_duration_since(earlier).unwrap_or(Duration::from_ticks(0))
+    }
+
+    /// The time elapsed since this Instant.
+    pub fn elapsed(&self) -> Duration {
+        Instant::now().duration_since(*self)
+    }
+
+    /// This Instant advanced by `duration`, or `None` if that would overflow.
+    pub fn checked_add(&self, duration: Duration) -> Option<Instant> {
+        self.0.checked_add_duration(duration).map(Instant)
+    }
+
+    /// This Instant moved back by `duration`, or `None` if that would overflow.
+    pub fn checked_sub(&self, duration: Duration) -> Option<Instant> {
+        self.0.checked_sub_duration(duration).map(Instant)
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Instant;
+
+    fn add(self, rhs: Duration) -> Instant {
+        Instant(self.0 + rhs)
+    }
+}
+
+impl AddAssign<Duration> for Instant {
+    fn add_assign(&mut self, rhs: Duration) {
+        self.0 += rhs;
+    }
+}
+
+impl Sub<Duration> for Instant {
+    type Output = Instant;
+
+    fn sub(self, rhs: Duration) -> Instant {
+        Instant(self.0 - rhs)
+    }
+}
+
+impl SubAssign<Duration> for Instant {
+    fn sub_assign(&mut self, rhs: Duration) {
+        self.0 -= rhs;
+    }
+}
+
+impl Sub<Instant> for Instant {
+    type Output = Duration;
+
+    fn sub(self, rhs: Instant) -> Duration {
+        self.0 - rhs.0
+    }
+}
+
+/// A span of time in 64-bit ticks, whatever the size of [`Tick`].  Used for the uptime, which
+/// Zephyr always counts in 64 bits, so that it doesn't wrap.
+pub type Uptime = fugit::Duration<u64, 1, SYS_FREQUENCY>;
+
+/// The time since the system started, from `k_uptime_ticks`.
+///
+/// Unlike [`Instant::now`], this is never truncated to 32 bits, so it is suitable for measuring
+/// long running times.  Use [`Instant`] for deadlines and timeouts.
+pub fn uptime() -> Uptime {
+    let ticks = unsafe { crate::raw::k_uptime_ticks() };
+    Uptime::from_ticks(ticks as u64)
+}
 
 // The Zephyr `k_timeout_t` represents several different types of intervals, based on the range of
 // the value.  It is a signed number of the same size as the Tick here, which effectively means it
@@ -63,29 +177,108 @@ pub type Instant = fugit::Instant<Tick, 1, SYS_FREQUENCY>;
 // -1: K_FOREVER: a time that never expires.
 // MIN .. -2: A wait for an absolute amount of ticks from the start of the system.
 //
-// The absolute time offset is only implemented when time is a 64-bit value.  This also means that
-// "Instant" isn't available when time is defined as a 32-bit value.
+// The absolute time offset is only implemented when time is a 64-bit value.  With 32-bit time, an
+// Instant is converted into a relative timeout instead.
 
 // Wrapper around the timeout type, so we can implement From/Info.
+//
+// There are two policies for converting time values into a Timeout.  The `From` conversions
+// saturate: a value too large to represent is clamped to the largest finite timeout of the same
+// kind, which, at any reasonable tick rate, is many years.  The `try_from_*` conversions instead
+// return a `TimeoutError` for these values.  Values within range convert exactly with either.
 pub struct Timeout(pub k_timeout_t);
 
-// `From` allows methods to take a time of various types and convert it into a Zephyr timeout.
+/// The error returned when a time value is out of range for a `Timeout`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimeoutError;
+
+impl fmt::Display for TimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "time value out of range for a timeout")
+    }
+}
+
+impl From<TimeoutError> for crate::error::Error {
+    fn from(_value: TimeoutError) -> crate::error::Error {
+        crate::error::Error(crate::raw::EINVAL)
+    }
+}
+
+/// The largest tick count of an absolute timeout.  The encoding of these is `-2 - ticks`, which
+/// must not go below the minimum `k_ticks_t`.
+#[cfg(CONFIG_TIMEOUT_64BIT)]
+const MAX_ABS_TICKS: k_ticks_t = k_ticks_t::MAX - 1;
+
+impl Timeout {
+    /// The time at which this timeout would expire, if it were started now, or `None` for
+    /// `Forever`.
+    fn deadline(&self) -> Option<Instant> {
+        let ticks = self.0.ticks;
+        if ticks == crate::sys::K_FOREVER.ticks {
+            None
+        } else if ticks >= 0 {
+            Some(Instant::now() + Duration::from_ticks(ticks as Tick))
+        } else {
+            // An absolute timeout, encoded as `-2 - ticks`.
+            Some(Instant::from_ticks((-1 - 1 - ticks) as Tick))
+        }
+    }
+
+    /// Convert a Duration to a relative timeout, returning an error if it is too large to be
+    /// represented.  A zero duration is the same as `NoWait`.
+    pub fn try_from_duration(value: Duration) -> Result<Timeout, TimeoutError> {
+        let ticks: k_ticks_t = value.ticks().try_into().map_err(|_| TimeoutError)?;
+        Ok(Timeout(k_timeout_t { ticks }))
+    }
+
+    /// Convert an Instant to an absolute timeout, returning an error if it is too far in the
+    /// future to be represented.
+    #[cfg(CONFIG_TIMEOUT_64BIT)]
+    pub fn try_from_instant(value: Instant) -> Result<Timeout, TimeoutError> {
+        let ticks: k_ticks_t = value.ticks().try_into().map_err(|_| TimeoutError)?;
+        if ticks > MAX_ABS_TICKS {
+            return Err(TimeoutError);
+        }
+        Ok(Timeout(k_timeout_t { ticks: -1 - 1 - ticks }))
+    }
+
+    /// Convert an Instant to a timeout.  Without 64-bit timeouts, this is a relative timeout, as
+    /// described for `From<Instant>`, and can't fail.
+    #[cfg(not(CONFIG_TIMEOUT_64BIT))]
+    pub fn try_from_instant(value: Instant) -> Result<Timeout, TimeoutError> {
+        Ok(value.into())
+    }
+}
+
+/// Saturating: a Duration too large to represent becomes the longest finite timeout.  A zero
+/// duration is the same as `NoWait`.
 impl From<Duration> for Timeout {
     fn from(value: Duration) -> Timeout {
-        let ticks: k_ticks_t = checked_cast(value.ticks());
-        debug_assert_ne!(ticks, crate::sys::K_FOREVER.ticks);
-        debug_assert_ne!(ticks, crate::sys::K_NO_WAIT.ticks);
-        Timeout(k_timeout_t { ticks })
+        Timeout::try_from_duration(value)
+            .unwrap_or(Timeout(k_timeout_t { ticks: k_ticks_t::MAX }))
     }
 }
 
+/// Saturating: an Instant too far in the future to represent becomes the latest absolute timeout.
 #[cfg(CONFIG_TIMEOUT_64BIT)]
 impl From<Instant> for Timeout {
     fn from(value: Instant) -> Timeout {
-        let ticks: k_ticks_t = checked_cast(value.ticks());
-        debug_assert_ne!(ticks, crate::sys::K_FOREVER.ticks);
-        debug_assert_ne!(ticks, crate::sys::K_NO_WAIT.ticks);
-        Timeout(k_timeout_t { ticks: -1 - 1 - ticks })
+        Timeout::try_from_instant(value)
+            .unwrap_or(Timeout(k_timeout_t { ticks: -1 - 1 - MAX_ABS_TICKS }))
+    }
+}
+
+/// Without 64-bit timeouts, the kernel has no absolute timeouts.  The deadline is converted to a
+/// timeout relative to the time of the conversion, so any delay between the conversion and the
+/// kernel call will delay the wakeup by that amount.  A deadline that has already passed becomes
+/// `NoWait`.  The remaining time always fits, so this conversion is exact.
+#[cfg(not(CONFIG_TIMEOUT_64BIT))]
+impl From<Instant> for Timeout {
+    fn from(value: Instant) -> Timeout {
+        match value.checked_duration_since(Instant::now()) {
+            Some(remaining) if remaining.ticks() > 0 => remaining.into(),
+            _ => NoWait.into(),
+        }
     }
 }
 
@@ -93,6 +286,7 @@ impl From<Instant> for Timeout {
 /// anywhere a timeout is needed.
 pub struct Forever;
 
+/// Exact: always `K_FOREVER`.
 impl From<Forever> for Timeout {
     fn from(_value: Forever) -> Timeout {
         Timeout(crate::sys::K_FOREVER)
@@ -103,6 +297,7 @@ impl From<Forever> for Timeout {
 /// anywhere a timeout is needed.
 pub struct NoWait;
 
+/// Exact: always `K_NO_WAIT`.
 impl From<NoWait> for Timeout {
     fn from(_valued: NoWait) -> Timeout {
         Timeout(crate::sys::K_NO_WAIT)
@@ -110,26 +305,62 @@ impl From<NoWait> for Timeout {
 }
 
 /// Put the current thread to sleep, for the given duration.  Uses `k_sleep` for the actual sleep.
-/// Returns a duration roughly representing the remaining amount of time if the sleep was woken.
+///
+/// Returns zero if the full time elapsed.  If the sleep was ended early by [`wakeup`], returns the
+/// time that was left until the end of the timeout, in ticks.  A sleep with `Forever` that is
+/// ended early returns the largest Duration.
 pub fn sleep<T>(timeout: T) -> Duration
     where T: Into<Timeout>,
 {
     let timeout: Timeout = timeout.into();
+    let deadline = timeout.deadline();
+    // The result is in milliseconds, which is too coarse to give the remaining time, but tells
+    // whether the sleep was ended early.
     let rest = unsafe { crate::raw::k_sleep(timeout.0) };
-    Duration::millis(rest as Tick)
+    if rest == 0 {
+        return Duration::from_ticks(0);
+    }
+    match deadline {
+        Some(deadline) => deadline.duration_since(Instant::now()),
+        None => Duration::from_ticks(Tick::MAX),
+    }
 }
 
-/// Convert from the Tick time type, which is unsigned, to the `k_ticks_t` type. When debug
-/// assertions are enabled, it will panic on overflow.
-fn checked_cast<I, O>(tick: I) -> O
-where
-    I: TryInto<O>,
-    I::Error: Debug,
-    O: Default,
-{
-    if cfg!(debug_assertions) {
-        tick.try_into().expect("Overflow in time conversion")
-    } else {
-        tick.try_into().unwrap_or(O::default())
-    }
+/// Put the current thread to sleep until the given Instant.  Because the deadline is absolute,
+/// repeatedly advancing it by a fixed period gives a loop that doesn't drift.
+pub fn sleep_until(deadline: Instant) {
+    sleep(deadline);
+}
+
+/// Put the current thread to sleep for the given number of microseconds, using `k_usleep`.  The
+/// sleep is rounded up to a whole number of ticks, so this is only finer grained than [`sleep`] on
+/// systems with a high tick rate.  Values above `i32::MAX` are clamped.
+///
+/// Returns zero if the full time elapsed, or the remaining time if the sleep was ended early by
+/// [`wakeup`].
+pub fn sleep_us(us: u32) -> Duration {
+    let us = us.min(i32::MAX as u32) as i32;
+    let rest = unsafe { crate::raw::k_usleep(us) };
+    Duration::micros_at_least(rest.max(0) as Tick)
+}
+
+/// Wait for the given number of microseconds without giving up the CPU, using `k_busy_wait`.
+///
+/// Unlike the sleeps, this can be used from interrupt context, and is accurate for delays shorter
+/// than a tick, but no other thread of the same or lower priority can run while waiting.
+pub fn busy_wait(us: u32) {
+    unsafe { crate::raw::k_busy_wait(us) }
+}
+
+/// Give up the CPU to other ready threads of the same or higher priority, using `k_yield`.  The
+/// current thread stays ready, and runs again once they have run.
+pub fn yield_now() {
+    unsafe { crate::raw::k_yield() }
+}
+
+/// End the sleep of the given thread early, using `k_wakeup`.  Its call to [`sleep`] or
+/// [`sleep_us`] returns the time that was remaining.  This has no effect if the thread is not
+/// sleeping.
+pub fn wakeup(thread: &JoinHandle) {
+    unsafe { crate::raw::k_wakeup(thread.tid()) }
 }
@@ -0,0 +1,121 @@
+// Copyright (c) 2024 Linaro LTD
+// SPDX-License-Identifier: Apache-2.0
+
+//! An `embassy-time` driver on the Zephyr system clock.
+//!
+//! This is enabled with the `time-driver` cargo feature.  The current time comes from
+//! `k_uptime_ticks`, and wakeups are scheduled with a single `k_timer`, which is always set for the
+//! earliest pending wakeup.
+//!
+//! The driver uses Zephyr ticks directly as the embassy tick, so the tick rate selected for
+//! `embassy-time-driver` (with its `tick-hz-*` features) must match
+//! `CONFIG_SYS_CLOCK_TICKS_PER_SEC`.  This is checked at compile time.
+
+use core::task::Waker;
+
+use embassy_time_driver::{time_driver_impl, Driver, TICK_HZ};
+
+use crate::object::ZephyrObject;
+use crate::raw::{self, k_timer};
+use crate::sys::{SpinMutex, K_NO_WAIT};
+use crate::time::{Duration, Tick, Timeout, SYS_FREQUENCY};
+
+const _: () = assert!(
+    TICK_HZ == SYS_FREQUENCY as u64,
+    "The embassy-time-driver tick-hz-* feature must match CONFIG_SYS_CLOCK_TICKS_PER_SEC",
+);
+
+/// The number of wakeups that can be pending at once.  When the queue is full, the entry with the
+/// latest deadline is woken early to make room, and its task will reschedule when it is polled.
+const QUEUE_SIZE: usize = 16;
+
+type Queue = [Option<(u64, Waker)>; QUEUE_SIZE];
+
+struct ZephyrTimeDriver {
+    queue: SpinMutex<Queue>,
+    alarm: ZephyrObject<k_timer>,
+}
+
+time_driver_impl!(static DRIVER: ZephyrTimeDriver = ZephyrTimeDriver {
+    queue: SpinMutex::new([const { None }; QUEUE_SIZE]),
+    alarm: ZephyrObject::new(),
+});
+
+impl ZephyrTimeDriver {
+    fn alarm(&self) -> *mut k_timer {
+        self.alarm.get(|t| unsafe {
+            raw::k_timer_init(t, Some(alarm_expiry), None);
+        })
+    }
+
+    /// Wake any expired entries, and set the alarm for the next one.  Must be called with the
+    /// queue locked.
+    fn update(&self, queue: &mut Queue) {
+        let now = self.now();
+        let mut next = u64::MAX;
+        for entry in queue.iter_mut() {
+            match entry {
+                Some((at, _)) if *at <= now => {
+                    if let Some((_, waker)) = entry.take() {
+                        waker.wake();
+                    }
+                }
+                Some((at, _)) => next = next.min(*at),
+                None => (),
+            }
+        }
+
+        let alarm = self.alarm();
+        if next == u64::MAX {
+            unsafe { raw::k_timer_stop(alarm) };
+        } else {
+            // The alarm is relative, so it may fire early or late by the time spent getting here.
+            // Firing early just results in another update.  The cast is only needed with 32-bit
+            // ticks.
+            #[allow(clippy::unnecessary_cast)]
+            let ticks = (next - now).min(Tick::MAX as u64) as Tick;
+            let timeout: Timeout = Duration::from_ticks(ticks).into();
+            unsafe { raw::k_timer_start(alarm, timeout.0, K_NO_WAIT) };
+        }
+    }
+}
+
+impl Driver for ZephyrTimeDriver {
+    fn now(&self) -> u64 {
+        unsafe { raw::k_uptime_ticks() as u64 }
+    }
+
+    fn schedule_wake(&self, at: u64, waker: &Waker) {
+        let mut queue = self.queue.lock();
+
+        if let Some((old, _)) = queue.iter_mut().flatten().find(|(_, w)| w.will_wake(waker)) {
+            if at >= *old {
+                return;
+            }
+            *old = at;
+        } else {
+            let slot = match queue.iter().position(|entry| entry.is_none()) {
+                Some(slot) => slot,
+                None => {
+                    let (latest, _) = queue
+                        .iter()
+                        .enumerate()
+                        .max_by_key(|(_, entry)| entry.as_ref().map(|(at, _)| *at))
+                        .unwrap();
+                    if let Some((_, evicted)) = queue[latest].take() {
+                        evicted.wake();
+                    }
+                    latest
+                }
+            };
+            queue[slot] = Some((at, waker.clone()));
+        }
+
+        self.update(&mut queue);
+    }
+}
+
+unsafe extern "C" fn alarm_expiry(_timer: *mut k_timer) {
+    let mut queue = DRIVER.queue.lock();
+    DRIVER.update(&mut queue);
+}
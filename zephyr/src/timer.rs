@@ -0,0 +1,252 @@
+// Copyright (c) 2024 Linaro LTD
+// SPDX-License-Identifier: Apache-2.0
+
+//! Kernel timers.
+//!
+//! A [`Timer`] wraps a `k_timer`.  It can be one-shot, or periodic, and can optionally call a
+//! handler each time it expires, and when it is stopped.  Threads can also wait for the timer to
+//! expire with [`Timer::status_sync`].
+//!
+//! As with the types in [`sync`](crate::sync), timers are set up on first use, and are intended to
+//! be `static` items.
+//!
+//! ```ignore
+//! fn tick(_timer: &Timer) {
+//!     // Called from interrupt context.
+//! }
+//!
+//! static TICKER: Timer = Timer::new().with_expiry(&tick);
+//!
+//! TICKER.start(Duration::millis(10), Duration::millis(10));
+//! ```
+//!
+//! For async code, [`sleep`] and [`sleep_until`] give futures that complete when a timer expires.
+
+use core::cell::{Cell, UnsafeCell};
+use core::fmt;
+use core::future::Future;
+use core::hint;
+use core::marker::PhantomPinned;
+use core::mem;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+
+use crate::object::ZephyrObject;
+use crate::raw::{self, k_timer};
+use crate::sys::{SpinMutex, K_NO_WAIT};
+use crate::time::{Duration, Instant, Tick, Timeout};
+
+/// A handler for timer events.  It is given the timer that triggered it.
+pub type TimerHandler = &'static (dyn Fn(&Timer) + Sync);
+
+/// A kernel timer.
+#[repr(C)]
+pub struct Timer {
+    // Must be first, as the trampolines recover the Timer from the `k_timer` pointer.
+    raw: ZephyrObject<k_timer>,
+    expiry: Option<TimerHandler>,
+    stop: Option<TimerHandler>,
+}
+
+impl Timer {
+    /// Create a new timer, with no handlers.
+    pub const fn new() -> Timer {
+        Timer {
+            raw: ZephyrObject::new(),
+            expiry: None,
+            stop: None,
+        }
+    }
+
+    /// Set the handler to be called each time the timer expires.  The handler is called from
+    /// interrupt context, and must not block.
+    pub const fn with_expiry(self, handler: TimerHandler) -> Timer {
+        Timer { expiry: Some(handler), ..self }
+    }
+
+    /// Set the handler to be called when the timer is stopped with [`stop`](Self::stop) while it
+    /// is running.  This is called in the context of the thread calling `stop`.
+    pub const fn with_stop(self, handler: TimerHandler) -> Timer {
+        Timer { stop: Some(handler), ..self }
+    }
+
+    fn raw(&self) -> *mut k_timer {
+        self.raw.get(|t| unsafe {
+            raw::k_timer_init(
+                t,
+                self.expiry.map(|_| timer_expiry as unsafe extern "C" fn(*mut k_timer)),
+                self.stop.map(|_| timer_stop as unsafe extern "C" fn(*mut k_timer)),
+            );
+        })
+    }
+
+    /// Start the timer.  It will first expire after `duration`, and then every `period` after
+    /// that.  A zero period gives a one-shot timer.  Starting a running timer restarts it, and
+    /// resets the status.
+    pub fn start(&self, duration: Duration, period: Duration) {
+        let duration: Timeout = duration.into();
+        let period: Timeout = period.into();
+        unsafe { raw::k_timer_start(self.raw(), duration.0, period.0) }
+    }
+
+    /// Stop the timer.  If it was running, the stop handler is called, and any threads waiting in
+    /// [`status_sync`](Self::status_sync) are woken.
+    pub fn stop(&self) {
+        unsafe { raw::k_timer_stop(self.raw()) }
+    }
+
+    /// The number of times the timer has expired since the status was last read, resetting the
+    /// count to zero.
+    pub fn status(&self) -> u32 {
+        unsafe { raw::k_timer_status_get(self.raw()) }
+    }
+
+    /// Wait for the timer to expire, unless it has already expired since the status was last
+    /// read.  Returns the number of times it expired, resetting the count to zero.  A result of
+    /// zero means the timer was stopped.
+    pub fn status_sync(&self) -> u32 {
+        unsafe { raw::k_timer_status_sync(self.raw()) }
+    }
+
+    /// The time remaining until the timer next expires, or zero if it is not running.
+    pub fn remaining(&self) -> Duration {
+        let ticks = unsafe { raw::k_timer_remaining_ticks(self.raw()) };
+        Duration::from_ticks(ticks as Tick)
+    }
+}
+
+impl Default for Timer {
+    fn default() -> Self {
+        Timer::new()
+    }
+}
+
+impl fmt::Debug for Timer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Timer {{ .. }}")
+    }
+}
+
+unsafe extern "C" fn timer_expiry(timer: *mut k_timer) {
+    let timer = &*(timer as *const Timer);
+    if let Some(handler) = timer.expiry {
+        handler(timer);
+    }
+}
+
+unsafe extern "C" fn timer_stop(timer: *mut k_timer) {
+    let timer = &*(timer as *const Timer);
+    if let Some(handler) = timer.stop {
+        handler(timer);
+    }
+}
+
+/// Wait for the given duration.
+pub fn sleep(duration: Duration) -> Sleep {
+    sleep_until(Instant::now() + duration)
+}
+
+/// Wait until the given Instant.
+pub fn sleep_until(deadline: Instant) -> Sleep {
+    Sleep {
+        timer: UnsafeCell::new(unsafe { mem::zeroed() }),
+        state: SpinMutex::new(SleepState { fired: false, stopped: false, waker: None }),
+        deadline,
+        started: Cell::new(false),
+        _pinned: PhantomPinned,
+    }
+}
+
+/// The future returned by [`sleep`] and [`sleep_until`].
+///
+/// A `k_timer` is started the first time this is polled, and wakes the task when it expires.  The
+/// wakeup comes from interrupt context, so this can be used with other executors only if their
+/// wakers can be used from interrupt handlers.
+#[repr(C)]
+pub struct Sleep {
+    // Must be first, as the expiry handler recovers the Sleep from the `k_timer` pointer.
+    timer: UnsafeCell<k_timer>,
+    state: SpinMutex<SleepState>,
+    deadline: Instant,
+    started: Cell<bool>,
+    _pinned: PhantomPinned,
+}
+
+/// The part of a [`Sleep`] shared with the expiry handler.  This is all under one lock, as on SMP
+/// the handler can run on another CPU between the check of `fired` and storing the waker.
+struct SleepState {
+    /// Set by the expiry handler, which doesn't touch the Sleep after releasing the lock.
+    fired: bool,
+    /// Set when `k_timer_stop` stopped the timer before it expired, so the handler will not run.
+    stopped: bool,
+    waker: Option<Waker>,
+}
+
+// The timer is only touched by the kernel, and the other fields shared with the expiry handler are
+// synchronized.
+unsafe impl Send for Sleep {}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        // Only shared access is used, as the expiry handler may be looking at this concurrently.
+        let this = self.as_ref().get_ref();
+        {
+            let mut state = this.state.lock();
+            if state.fired || Instant::now() >= this.deadline {
+                return Poll::Ready(());
+            }
+            state.waker = Some(cx.waker().clone());
+        }
+
+        if !this.started.get() {
+            let timeout: Timeout = this.deadline.into();
+            unsafe {
+                raw::k_timer_init(this.timer.get(), Some(sleep_expiry), Some(sleep_stop));
+                raw::k_timer_start(this.timer.get(), timeout.0, K_NO_WAIT);
+            }
+            this.started.set(true);
+        }
+        Poll::Pending
+    }
+}
+
+impl Drop for Sleep {
+    fn drop(&mut self) {
+        if !self.started.get() {
+            return;
+        }
+        unsafe { raw::k_timer_stop(self.timer.get()) };
+
+        // `k_timer_stop` doesn't wait for an expiry handler that is already running on another
+        // CPU.  If the stop didn't catch the timer, the handler has run, or is about to, so wait
+        // for it to be done with this Sleep before it is freed.
+        loop {
+            let state = self.state.lock();
+            if state.fired || state.stopped {
+                break;
+            }
+            drop(state);
+            hint::spin_loop();
+        }
+    }
+}
+
+unsafe extern "C" fn sleep_expiry(timer: *mut k_timer) {
+    let sleep = &*(timer as *const Sleep);
+    let waker = {
+        let mut state = sleep.state.lock();
+        state.fired = true;
+        state.waker.take()
+    };
+    if let Some(waker) = waker {
+        waker.wake();
+    }
+}
+
+/// Called by `k_timer_stop`, only if it stopped the timer before it expired.
+unsafe extern "C" fn sleep_stop(timer: *mut k_timer) {
+    let sleep = &*(timer as *const Sleep);
+    sleep.state.lock().stopped = true;
+}
@@ -0,0 +1,293 @@
+// Copyright (c) 2024 Linaro LTD
+// SPDX-License-Identifier: Apache-2.0
+
+//! Work queues.
+//!
+//! A work item holds a handler that is run on a work queue thread.  This is the usual way to defer
+//! processing out of interrupt context: an interrupt handler submits the work item, and the
+//! handler runs later, in a thread, where it is able to block.
+//!
+//! There are two kinds of work item.  A [`Work`] is run as soon as the queue gets to it, and a
+//! [`DelayableWork`] can also be scheduled to be submitted after a delay.  Work can be submitted
+//! to the system work queue, or to a dedicated [`WorkQueue`], which runs on its own thread.
+//!
+//! As with the types in [`sync`](crate::sync), these are set up on first use, and are intended to
+//! be `static` items.
+//!
+//! ```ignore
+//! fn process(_work: &Work) {
+//!     // Runs in the work queue thread.
+//! }
+//!
+//! static PROCESS: Work = Work::new(&process);
+//!
+//! // From an interrupt handler:
+//! PROCESS.submit()?;
+//! ```
+
+use core::ffi::{c_int, CStr};
+use core::fmt;
+use core::mem;
+
+use crate::error::{to_result, to_result_void, Error, Result};
+use crate::object::ZephyrObject;
+use crate::raw::{self, k_work, k_work_delayable, k_work_q};
+use crate::sys::SpinMutex;
+use crate::thread::{AlignAs, Alignment, ThreadStack};
+use crate::time::{Duration, Tick, Timeout};
+
+/// The handler for a [`Work`] item.
+pub type WorkHandler = &'static (dyn Fn(&Work) + Sync);
+
+/// The handler for a [`DelayableWork`] item.
+pub type DelayableWorkHandler = &'static (dyn Fn(&DelayableWork) + Sync);
+
+/// A work item, wrapping `k_work`.
+#[repr(C)]
+pub struct Work {
+    // Must be first, as the trampoline recovers the Work from the `k_work` pointer.
+    raw: ZephyrObject<k_work>,
+    handler: WorkHandler,
+}
+
+impl Work {
+    /// Create a work item that runs the given handler.
+    pub const fn new(handler: WorkHandler) -> Work {
+        Work {
+            raw: ZephyrObject::new(),
+            handler,
+        }
+    }
+
+    fn raw(&self) -> *mut k_work {
+        self.raw.get(|w| unsafe {
+            raw::k_work_init(w, Some(work_trampoline));
+        })
+    }
+
+    /// Submit the work to the system work queue.  Returns true if it was queued, or false if it was
+    /// already queued.  This can be called from interrupt context.
+    pub fn submit(&self) -> Result<bool> {
+        to_result(unsafe { raw::k_work_submit(self.raw()) }).map(|code| code != 0)
+    }
+
+    /// Submit the work to the given queue.  Returns true if it was queued, or false if it was
+    /// already queued.  This can be called from interrupt context.
+    pub fn submit_to_queue(&self, queue: &WorkQueue) -> Result<bool> {
+        to_result(unsafe { raw::k_work_submit_to_queue(queue.raw(), self.raw()) })
+            .map(|code| code != 0)
+    }
+
+    /// Determine if the work is queued, or running.
+    pub fn is_pending(&self) -> bool {
+        unsafe { raw::k_work_is_pending(self.raw()) }
+    }
+
+    /// Cancel the work if it is queued.  This does not wait for a running handler to complete.
+    /// Returns true if the work is still busy, either because it is running, or because it could
+    /// not be removed from the queue.
+    pub fn cancel(&self) -> bool {
+        unsafe { raw::k_work_cancel(self.raw()) != 0 }
+    }
+
+    /// Cancel the work, and wait for the handler to complete if it is running.  Returns true if the
+    /// work was pending.  This must not be called from interrupt context.
+    pub fn cancel_sync(&self) -> bool {
+        let mut sync: raw::k_work_sync = unsafe { mem::zeroed() };
+        unsafe { raw::k_work_cancel_sync(self.raw(), &mut sync) }
+    }
+}
+
+impl fmt::Debug for Work {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Work {{ .. }}")
+    }
+}
+
+unsafe extern "C" fn work_trampoline(work: *mut k_work) {
+    let work = &*(work as *const Work);
+    (work.handler)(work);
+}
+
+/// A work item that can be scheduled after a delay, wrapping `k_work_delayable`.
+#[repr(C)]
+pub struct DelayableWork {
+    // Must be first, as the trampoline recovers the DelayableWork from the `k_work_delayable`
+    // pointer.
+    raw: ZephyrObject<k_work_delayable>,
+    handler: DelayableWorkHandler,
+}
+
+impl DelayableWork {
+    /// Create a delayable work item that runs the given handler.
+    pub const fn new(handler: DelayableWorkHandler) -> DelayableWork {
+        DelayableWork {
+            raw: ZephyrObject::new(),
+            handler,
+        }
+    }
+
+    fn raw(&self) -> *mut k_work_delayable {
+        self.raw.get(|w| unsafe {
+            raw::k_work_init_delayable(w, Some(delayable_work_trampoline));
+        })
+    }
+
+    /// Schedule the work on the system work queue after the given delay.  If the work is already
+    /// scheduled or queued, this has no effect.  Returns true if the work was scheduled.
+    pub fn schedule<T>(&self, delay: T) -> Result<bool>
+        where T: Into<Timeout>,
+    {
+        let delay: Timeout = delay.into();
+        to_result(unsafe { raw::k_work_schedule(self.raw(), delay.0) }).map(|code| code != 0)
+    }
+
+    /// Like [`schedule`](Self::schedule), but for the given queue.
+    pub fn schedule_for_queue<T>(&self, queue: &WorkQueue, delay: T) -> Result<bool>
+        where T: Into<Timeout>,
+    {
+        let delay: Timeout = delay.into();
+        to_result(unsafe { raw::k_work_schedule_for_queue(queue.raw(), self.raw(), delay.0) })
+            .map(|code| code != 0)
+    }
+
+    /// Schedule the work on the system work queue after the given delay, replacing any existing
+    /// schedule.  Returns true if the work was scheduled.
+    pub fn reschedule<T>(&self, delay: T) -> Result<bool>
+        where T: Into<Timeout>,
+    {
+        let delay: Timeout = delay.into();
+        to_result(unsafe { raw::k_work_reschedule(self.raw(), delay.0) }).map(|code| code != 0)
+    }
+
+    /// Like [`reschedule`](Self::reschedule), but for the given queue.
+    pub fn reschedule_for_queue<T>(&self, queue: &WorkQueue, delay: T) -> Result<bool>
+        where T: Into<Timeout>,
+    {
+        let delay: Timeout = delay.into();
+        to_result(unsafe { raw::k_work_reschedule_for_queue(queue.raw(), self.raw(), delay.0) })
+            .map(|code| code != 0)
+    }
+
+    /// Determine if the work is scheduled, queued, or running.
+    pub fn is_pending(&self) -> bool {
+        unsafe { raw::k_work_delayable_is_pending(self.raw()) }
+    }
+
+    /// The time remaining before scheduled work is submitted, or zero if it isn't scheduled.
+    pub fn remaining(&self) -> Duration {
+        let ticks = unsafe { raw::k_work_delayable_remaining_get(self.raw()) };
+        Duration::from_ticks(ticks as Tick)
+    }
+
+    /// Cancel the work if it is scheduled or queued.  This does not wait for a running handler to
+    /// complete.  Returns true if the work is still busy.
+    pub fn cancel(&self) -> bool {
+        unsafe { raw::k_work_cancel_delayable(self.raw()) != 0 }
+    }
+
+    /// Cancel the work, and wait for the handler to complete if it is running.  Returns true if the
+    /// work was pending.  This must not be called from interrupt context.
+    pub fn cancel_sync(&self) -> bool {
+        let mut sync: raw::k_work_sync = unsafe { mem::zeroed() };
+        unsafe { raw::k_work_cancel_delayable_sync(self.raw(), &mut sync) }
+    }
+}
+
+impl fmt::Debug for DelayableWork {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "DelayableWork {{ .. }}")
+    }
+}
+
+unsafe extern "C" fn delayable_work_trampoline(work: *mut k_work) {
+    let work = &*(raw::k_work_delayable_from_work(work) as *const DelayableWork);
+    (work.handler)(work);
+}
+
+/// A dedicated work queue, with its own thread.
+///
+/// ```ignore
+/// zephyr::thread_stack!(QUEUE_STACK, 2048);
+/// static QUEUE: WorkQueue = WorkQueue::new();
+///
+/// QUEUE.start(&QUEUE_STACK, 5, Some(c"my_queue"))?;
+/// PROCESS.submit_to_queue(&QUEUE)?;
+/// ```
+pub struct WorkQueue {
+    raw: ZephyrObject<k_work_q>,
+    started: SpinMutex<bool>,
+}
+
+impl WorkQueue {
+    /// Create a new work queue.  It must be started before work submitted to it will run.
+    pub const fn new() -> WorkQueue {
+        WorkQueue {
+            raw: ZephyrObject::new(),
+            started: SpinMutex::new(false),
+        }
+    }
+
+    fn raw(&self) -> *mut k_work_q {
+        self.raw.get(|q| unsafe {
+            raw::k_work_queue_init(q);
+        })
+    }
+
+    /// Start the queue's thread, using the given stack and priority.  Fails with `EBUSY` if the
+    /// queue has already been started, or the stack is in use.  The stack remains in use by the
+    /// queue from then on.
+    pub fn start<const SIZE: usize, const ALIGN: usize>(
+        &'static self,
+        stack: &'static ThreadStack<SIZE, ALIGN>,
+        priority: c_int,
+        name: Option<&'static CStr>,
+    ) -> Result<()>
+        where AlignAs<ALIGN>: Alignment,
+    {
+        let mut started = self.started.lock();
+        if *started {
+            return Err(Error(raw::EBUSY));
+        }
+        stack.claim()?;
+        *started = true;
+        drop(started);
+
+        let mut config: raw::k_work_queue_config = unsafe { mem::zeroed() };
+        if let Some(name) = name {
+            config.name = name.as_ptr();
+        }
+        unsafe {
+            raw::k_work_queue_start(self.raw(), stack.as_raw(), stack.size(), priority, &config);
+        }
+        Ok(())
+    }
+
+    /// The thread running this queue, once it has been started.
+    pub fn thread(&self) -> raw::k_tid_t {
+        unsafe { raw::k_work_queue_thread_get(self.raw()) }
+    }
+
+    /// Wait until the queue is empty.  If `plug` is true, new submissions to the queue are
+    /// rejected until it is unplugged.
+    pub fn drain(&self, plug: bool) -> Result<()> {
+        to_result_void(unsafe { raw::k_work_queue_drain(self.raw(), plug) })
+    }
+
+    /// Allow submissions to a queue that was plugged by [`drain`](Self::drain).
+    pub fn unplug(&self) -> Result<()> {
+        to_result_void(unsafe { raw::k_work_queue_unplug(self.raw()) })
+    }
+}
+
+impl Default for WorkQueue {
+    fn default() -> Self {
+        WorkQueue::new()
+    }
+}
+
+impl fmt::Debug for WorkQueue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "WorkQueue {{ .. }}")
+    }
+}